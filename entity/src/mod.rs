@@ -2,5 +2,20 @@
 
 pub mod prelude;
 
+pub mod attachment_thumbnail;
+pub mod bookmark;
+pub mod bookmark_collection;
+pub mod comment;
+pub mod follow;
+pub mod media;
 pub mod post;
+pub mod post_attachment;
+pub mod post_referrer_daily;
+pub mod post_revision;
+pub mod post_slug_history;
+pub mod post_tag;
+pub mod post_view_daily;
+pub mod reaction;
+pub mod sea_orm_active_enums;
+pub mod tag;
 pub mod user;
@@ -1,7 +1,12 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.11
 
+// `DeriveEntityModel` refers to active enum columns by a fully qualified `ValueType` path
+#![allow(unused_qualifications)]
+
 use sea_orm::entity::prelude::*;
 
+use super::sea_orm_active_enums::{PostStatus, PostVisibility};
+
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
 #[sea_orm(table_name = "post")]
 pub struct Model {
@@ -11,14 +16,38 @@ pub struct Model {
     pub text: String,
     #[sea_orm(unique)]
     pub uuid: Uuid,
-    pub image: Option<String>,
     pub user_id: i32,
     pub created_at: DateTimeWithTimeZone,
     pub updated_at: Option<DateTimeWithTimeZone>,
+    pub deleted_at: Option<DateTimeWithTimeZone>,
+    pub status: PostStatus,
+    pub published_at: Option<DateTimeWithTimeZone>,
+    pub text_html: Option<String>,
+    #[sea_orm(unique)]
+    pub slug: String,
+    pub visibility: PostVisibility,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::bookmark::Entity")]
+    Bookmark,
+    #[sea_orm(has_many = "super::comment::Entity")]
+    Comment,
+    #[sea_orm(has_many = "super::post_attachment::Entity")]
+    PostAttachment,
+    #[sea_orm(has_many = "super::post_referrer_daily::Entity")]
+    PostReferrerDaily,
+    #[sea_orm(has_many = "super::post_revision::Entity")]
+    PostRevision,
+    #[sea_orm(has_many = "super::post_slug_history::Entity")]
+    PostSlugHistory,
+    #[sea_orm(has_many = "super::post_tag::Entity")]
+    PostTag,
+    #[sea_orm(has_many = "super::post_view_daily::Entity")]
+    PostViewDaily,
+    #[sea_orm(has_many = "super::reaction::Entity")]
+    Reaction,
     #[sea_orm(
         belongs_to = "super::user::Entity",
         from = "Column::UserId",
@@ -29,10 +58,82 @@ pub enum Relation {
     User,
 }
 
+impl Related<super::bookmark::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Bookmark.def()
+    }
+}
+
+impl Related<super::comment::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Comment.def()
+    }
+}
+
+impl Related<super::post_attachment::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PostAttachment.def()
+    }
+}
+
+impl Related<super::post_referrer_daily::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PostReferrerDaily.def()
+    }
+}
+
+impl Related<super::post_revision::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PostRevision.def()
+    }
+}
+
+impl Related<super::post_slug_history::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PostSlugHistory.def()
+    }
+}
+
+impl Related<super::post_tag::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PostTag.def()
+    }
+}
+
+impl Related<super::post_view_daily::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PostViewDaily.def()
+    }
+}
+
+impl Related<super::reaction::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Reaction.def()
+    }
+}
+
 impl Related<super::user::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::User.def()
     }
 }
 
+impl Related<super::bookmark_collection::Entity> for Entity {
+    fn to() -> RelationDef {
+        super::bookmark::Relation::BookmarkCollection.def()
+    }
+    fn via() -> Option<RelationDef> {
+        Some(super::bookmark::Relation::Post.def().rev())
+    }
+}
+
+impl Related<super::tag::Entity> for Entity {
+    fn to() -> RelationDef {
+        super::post_tag::Relation::Tag.def()
+    }
+    fn via() -> Option<RelationDef> {
+        Some(super::post_tag::Relation::Post.def().rev())
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
@@ -1,4 +1,18 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.11
 
+pub use super::attachment_thumbnail::Entity as AttachmentThumbnail;
+pub use super::bookmark::Entity as Bookmark;
+pub use super::bookmark_collection::Entity as BookmarkCollection;
+pub use super::comment::Entity as Comment;
+pub use super::follow::Entity as Follow;
+pub use super::media::Entity as Media;
 pub use super::post::Entity as Post;
+pub use super::post_attachment::Entity as PostAttachment;
+pub use super::post_referrer_daily::Entity as PostReferrerDaily;
+pub use super::post_revision::Entity as PostRevision;
+pub use super::post_slug_history::Entity as PostSlugHistory;
+pub use super::post_tag::Entity as PostTag;
+pub use super::post_view_daily::Entity as PostViewDaily;
+pub use super::reaction::Entity as Reaction;
+pub use super::tag::Entity as Tag;
 pub use super::user::Entity as User;
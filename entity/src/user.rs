@@ -15,8 +15,28 @@ pub struct Model {
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::bookmark_collection::Entity")]
+    BookmarkCollection,
+    #[sea_orm(has_many = "super::comment::Entity")]
+    Comment,
     #[sea_orm(has_many = "super::post::Entity")]
     Post,
+    #[sea_orm(has_many = "super::post_revision::Entity")]
+    PostRevision,
+    #[sea_orm(has_many = "super::reaction::Entity")]
+    Reaction,
+}
+
+impl Related<super::bookmark_collection::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::BookmarkCollection.def()
+    }
+}
+
+impl Related<super::comment::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Comment.def()
+    }
 }
 
 impl Related<super::post::Entity> for Entity {
@@ -25,4 +45,16 @@ impl Related<super::post::Entity> for Entity {
     }
 }
 
+impl Related<super::post_revision::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PostRevision.def()
+    }
+}
+
+impl Related<super::reaction::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Reaction.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
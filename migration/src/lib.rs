@@ -2,6 +2,23 @@ pub use sea_orm_migration::prelude::*;
 
 mod m20220101_000001_create_table;
 mod m20250525_145126_create_user_table;
+mod m20261019_090000_add_deleted_at_to_post;
+mod m20261019_091000_add_post_pagination_index;
+mod m20261019_092000_add_post_search_vector;
+mod m20261019_093000_create_comment_table;
+mod m20261019_094000_create_reaction_table;
+mod m20261019_095000_create_tag_tables;
+mod m20261019_096000_add_status_to_post;
+mod m20261019_097000_create_post_revision_table;
+mod m20261019_098000_add_text_html_to_post;
+mod m20261019_099000_add_slug_to_post;
+mod m20261019_100000_create_post_attachment_table;
+mod m20261019_101000_create_attachment_thumbnail_table;
+mod m20261019_102000_create_media_table;
+mod m20261019_103000_add_visibility_to_post;
+mod m20261019_104000_create_follow_table;
+mod m20261019_105000_create_bookmark_tables;
+mod m20261019_106000_create_post_view_tables;
 
 #[derive(Debug)]
 pub struct Migrator;
@@ -12,6 +29,23 @@ impl MigratorTrait for Migrator {
         vec![
             Box::new(m20250525_145126_create_user_table::Migration),
             Box::new(m20220101_000001_create_table::Migration),
+            Box::new(m20261019_090000_add_deleted_at_to_post::Migration),
+            Box::new(m20261019_091000_add_post_pagination_index::Migration),
+            Box::new(m20261019_092000_add_post_search_vector::Migration),
+            Box::new(m20261019_093000_create_comment_table::Migration),
+            Box::new(m20261019_094000_create_reaction_table::Migration),
+            Box::new(m20261019_095000_create_tag_tables::Migration),
+            Box::new(m20261019_096000_add_status_to_post::Migration),
+            Box::new(m20261019_097000_create_post_revision_table::Migration),
+            Box::new(m20261019_098000_add_text_html_to_post::Migration),
+            Box::new(m20261019_099000_add_slug_to_post::Migration),
+            Box::new(m20261019_100000_create_post_attachment_table::Migration),
+            Box::new(m20261019_101000_create_attachment_thumbnail_table::Migration),
+            Box::new(m20261019_102000_create_media_table::Migration),
+            Box::new(m20261019_103000_add_visibility_to_post::Migration),
+            Box::new(m20261019_104000_create_follow_table::Migration),
+            Box::new(m20261019_105000_create_bookmark_tables::Migration),
+            Box::new(m20261019_106000_create_post_view_tables::Migration),
         ]
     }
 }
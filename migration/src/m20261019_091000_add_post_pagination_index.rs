@@ -0,0 +1,62 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName, Debug)]
+pub(crate) struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Keyset pagination walks posts by `(created_at, id)`
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-post-created_at-id")
+                    .table(Post::Table)
+                    .col(Post::CreatedAt)
+                    .col(Post::Id)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Same order, restricted to the posts of one user
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-post-user_id-created_at-id")
+                    .table(Post::Table)
+                    .col(Post::UserId)
+                    .col(Post::CreatedAt)
+                    .col(Post::Id)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-post-user_id-created_at-id")
+                    .table(Post::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-post-created_at-id")
+                    .table(Post::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Post {
+    Table,
+    Id,
+    UserId,
+    CreatedAt,
+}
@@ -0,0 +1,64 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName, Debug)]
+pub(crate) struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Titles weigh more than the text when ranking results
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Post::Table)
+                    .add_column(
+                        ColumnDef::new(Post::SearchVector)
+                            .custom(Alias::new("tsvector"))
+                            .extra(
+                                "GENERATED ALWAYS AS (setweight(to_tsvector('english', \
+                                 coalesce(title, '')), 'A') || setweight(to_tsvector('english', \
+                                 coalesce(text, '')), 'B')) STORED",
+                            ),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-post-search_vector")
+                    .table(Post::Table)
+                    .col(Post::SearchVector)
+                    .full_text()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-post-search_vector")
+                    .table(Post::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Post::Table)
+                    .drop_column(Post::SearchVector)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Post {
+    Table,
+    SearchVector,
+}
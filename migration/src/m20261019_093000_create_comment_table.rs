@@ -0,0 +1,83 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::{m20220101_000001_create_table::Post, m20250525_145126_create_user_table::User};
+
+#[derive(DeriveMigrationName, Debug)]
+pub(crate) struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Comment::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Comment::Id))
+                    .col(uuid_uniq(Comment::Uuid))
+                    .col(integer(Comment::PostId))
+                    .col(integer(Comment::UserId))
+                    .col(integer_null(Comment::ParentId))
+                    .col(text(Comment::Text))
+                    .col(timestamp_with_time_zone(Comment::CreatedAt))
+                    .col(timestamp_with_time_zone_null(Comment::UpdatedAt))
+                    .col(timestamp_with_time_zone_null(Comment::DeletedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-comments-posts-id")
+                            .from(Comment::Table, Comment::PostId)
+                            .to(Post::Table, Post::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-comments-users-id")
+                            .from(Comment::Table, Comment::UserId)
+                            .to(User::Table, User::Id),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-comments-comments-parent_id")
+                            .from(Comment::Table, Comment::ParentId)
+                            .to(Comment::Table, Comment::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Comments are listed per post and parent, oldest first
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-comment-post_id-parent_id-created_at-id")
+                    .table(Comment::Table)
+                    .col(Comment::PostId)
+                    .col(Comment::ParentId)
+                    .col(Comment::CreatedAt)
+                    .col(Comment::Id)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Comment::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Comment {
+    Table,
+    Id,
+    Uuid,
+    PostId,
+    UserId,
+    ParentId,
+    Text,
+    CreatedAt,
+    UpdatedAt,
+    DeletedAt,
+}
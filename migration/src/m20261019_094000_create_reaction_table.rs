@@ -0,0 +1,69 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::{m20220101_000001_create_table::Post, m20250525_145126_create_user_table::User};
+
+#[derive(DeriveMigrationName, Debug)]
+pub(crate) struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Reaction::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Reaction::Id))
+                    .col(integer(Reaction::UserId))
+                    .col(integer(Reaction::PostId))
+                    .col(string_len(Reaction::Kind, 32))
+                    .col(timestamp_with_time_zone(Reaction::CreatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-reactions-users-id")
+                            .from(Reaction::Table, Reaction::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-reactions-posts-id")
+                            .from(Reaction::Table, Reaction::PostId)
+                            .to(Post::Table, Post::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // A user reacts at most once per kind on a post. Also serves the per post counts.
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-reaction-post_id-kind-user_id")
+                    .table(Reaction::Table)
+                    .col(Reaction::PostId)
+                    .col(Reaction::Kind)
+                    .col(Reaction::UserId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Reaction::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Reaction {
+    Table,
+    Id,
+    UserId,
+    PostId,
+    Kind,
+    CreatedAt,
+}
@@ -0,0 +1,88 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20220101_000001_create_table::Post;
+
+#[derive(DeriveMigrationName, Debug)]
+pub(crate) struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Tag::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Tag::Id))
+                    .col(string_len_uniq(Tag::Name, 64))
+                    .col(timestamp_with_time_zone(Tag::CreatedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(PostTag::Table)
+                    .if_not_exists()
+                    .col(integer(PostTag::PostId))
+                    .col(integer(PostTag::TagId))
+                    .col(boolean(PostTag::Explicit))
+                    .primary_key(Index::create().col(PostTag::PostId).col(PostTag::TagId))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-post_tags-posts-id")
+                            .from(PostTag::Table, PostTag::PostId)
+                            .to(Post::Table, Post::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-post_tags-tags-id")
+                            .from(PostTag::Table, PostTag::TagId)
+                            .to(Tag::Table, Tag::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Listing the posts of a tag goes from the tag to its posts
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-post_tag-tag_id-post_id")
+                    .table(PostTag::Table)
+                    .col(PostTag::TagId)
+                    .col(PostTag::PostId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PostTag::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(Tag::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Tag {
+    Table,
+    Id,
+    Name,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum PostTag {
+    Table,
+    PostId,
+    TagId,
+    Explicit,
+}
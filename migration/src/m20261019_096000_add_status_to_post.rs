@@ -0,0 +1,106 @@
+use sea_orm_migration::{
+    prelude::{extension::postgres::Type, *},
+    schema::*,
+};
+
+#[derive(DeriveMigrationName, Debug)]
+pub(crate) struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(PostStatus::Enum)
+                    .values([
+                        PostStatus::Draft,
+                        PostStatus::Scheduled,
+                        PostStatus::Published,
+                        PostStatus::Archived,
+                    ])
+                    .to_owned(),
+            )
+            .await?;
+
+        // Posts created before statuses existed were published right away
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Post::Table)
+                    .add_column(
+                        ColumnDef::new(Post::Status)
+                            .custom(PostStatus::Enum)
+                            .not_null()
+                            .default("published"),
+                    )
+                    .add_column(timestamp_with_time_zone_null(Post::PublishedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(Post::Table)
+                    .value(Post::PublishedAt, Expr::col(Post::CreatedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        // The scheduler looks for scheduled posts which are due
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-post-status-published_at")
+                    .table(Post::Table)
+                    .col(Post::Status)
+                    .col(Post::PublishedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-post-status-published_at")
+                    .table(Post::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Post::Table)
+                    .drop_column(Post::Status)
+                    .drop_column(Post::PublishedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_type(Type::drop().name(PostStatus::Enum).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Post {
+    Table,
+    Status,
+    PublishedAt,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum PostStatus {
+    #[sea_orm(iden = "post_status")]
+    Enum,
+    Draft,
+    Scheduled,
+    Published,
+    Archived,
+}
@@ -0,0 +1,71 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::{m20220101_000001_create_table::Post, m20250525_145126_create_user_table::User};
+
+#[derive(DeriveMigrationName, Debug)]
+pub(crate) struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PostRevision::Table)
+                    .if_not_exists()
+                    .col(pk_auto(PostRevision::Id))
+                    .col(integer(PostRevision::PostId))
+                    .col(integer(PostRevision::Number))
+                    .col(string(PostRevision::Title))
+                    .col(text(PostRevision::Text))
+                    .col(integer(PostRevision::EditorId))
+                    .col(timestamp_with_time_zone(PostRevision::CreatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-post_revisions-posts-id")
+                            .from(PostRevision::Table, PostRevision::PostId)
+                            .to(Post::Table, Post::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-post_revisions-users-id")
+                            .from(PostRevision::Table, PostRevision::EditorId)
+                            .to(User::Table, User::Id),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Revisions are numbered per post
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-post_revision-post_id-number")
+                    .table(PostRevision::Table)
+                    .col(PostRevision::PostId)
+                    .col(PostRevision::Number)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PostRevision::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum PostRevision {
+    Table,
+    Id,
+    PostId,
+    Number,
+    Title,
+    Text,
+    EditorId,
+    CreatedAt,
+}
@@ -0,0 +1,143 @@
+use std::collections::HashSet;
+
+use sea_orm_migration::{
+    prelude::*,
+    schema::*,
+    sea_orm::{ConnectionTrait, Statement},
+};
+
+use crate::m20220101_000001_create_table::Post;
+
+#[derive(DeriveMigrationName, Debug)]
+pub(crate) struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Post::Table)
+                    .add_column(string_null(PostSlug::Slug))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Existing posts get slugs from their titles, oldest posts first to the plain ones
+        let db = manager.get_connection();
+        let rows = db
+            .query_all(Statement::from_string(
+                manager.get_database_backend(),
+                "SELECT id, title FROM post ORDER BY id",
+            ))
+            .await?;
+        let mut taken = HashSet::new();
+        for row in rows {
+            let id: i32 = row.try_get("", "id")?;
+            let title: String = row.try_get("", "title")?;
+            let base = match slug::slugify(&title) {
+                base if base.is_empty() => "post".to_string(),
+                base => base
+                    .chars()
+                    .take(80)
+                    .collect::<String>()
+                    .trim_end_matches('-')
+                    .to_string(),
+            };
+            let slug = (1..)
+                .map(|suffix| match suffix {
+                    1 => base.clone(),
+                    suffix => format!("{}-{}", base, suffix),
+                })
+                .find(|slug| !taken.contains(slug))
+                .unwrap_or_default();
+            taken.insert(slug.clone());
+            manager
+                .exec_stmt(
+                    Query::update()
+                        .table(Post::Table)
+                        .value(PostSlug::Slug, slug)
+                        .and_where(Expr::col(Post::Id).eq(id))
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Post::Table)
+                    .modify_column(ColumnDef::new(PostSlug::Slug).string().not_null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-post-slug")
+                    .table(Post::Table)
+                    .col(PostSlug::Slug)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // Slugs a post had before its title changed, redirecting to the current one
+        manager
+            .create_table(
+                Table::create()
+                    .table(PostSlugHistory::Table)
+                    .if_not_exists()
+                    .col(string(PostSlugHistory::Slug).primary_key())
+                    .col(integer(PostSlugHistory::PostId))
+                    .col(timestamp_with_time_zone(PostSlugHistory::CreatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-post_slug_history-posts-id")
+                            .from(PostSlugHistory::Table, PostSlugHistory::PostId)
+                            .to(Post::Table, Post::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-post_slug_history-post_id")
+                    .table(PostSlugHistory::Table)
+                    .col(PostSlugHistory::PostId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PostSlugHistory::Table).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Post::Table)
+                    .drop_column(PostSlug::Slug)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum PostSlug {
+    Slug,
+}
+
+#[derive(DeriveIden)]
+enum PostSlugHistory {
+    Table,
+    Slug,
+    PostId,
+    CreatedAt,
+}
@@ -0,0 +1,143 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20220101_000001_create_table::Post;
+
+#[derive(DeriveMigrationName, Debug)]
+pub(crate) struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PostAttachment::Table)
+                    .if_not_exists()
+                    .col(pk_auto(PostAttachment::Id))
+                    .col(uuid_uniq(PostAttachment::Uuid))
+                    .col(integer(PostAttachment::PostId))
+                    .col(integer(PostAttachment::Position))
+                    .col(string(PostAttachment::FileName))
+                    .col(string_null(PostAttachment::AltText))
+                    .col(integer_null(PostAttachment::Width))
+                    .col(integer_null(PostAttachment::Height))
+                    .col(timestamp_with_time_zone(PostAttachment::CreatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-post_attachments-posts-id")
+                            .from(PostAttachment::Table, PostAttachment::PostId)
+                            .to(Post::Table, Post::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Attachments are listed per post in their order
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-post_attachment-post_id-position")
+                    .table(PostAttachment::Table)
+                    .col(PostAttachment::PostId)
+                    .col(PostAttachment::Position)
+                    .to_owned(),
+            )
+            .await?;
+
+        // The single image of existing posts becomes their first attachment. Its dimensions
+        // were never recorded.
+        manager
+            .exec_stmt(
+                Query::insert()
+                    .into_table(PostAttachment::Table)
+                    .columns([
+                        PostAttachment::Uuid,
+                        PostAttachment::PostId,
+                        PostAttachment::Position,
+                        PostAttachment::FileName,
+                        PostAttachment::CreatedAt,
+                    ])
+                    .select_from(
+                        Query::select()
+                            .expr(Func::cust(Alias::new("gen_random_uuid")))
+                            .column(Post::Id)
+                            .expr(Expr::val(0))
+                            .column(Post::Image)
+                            .column(Post::CreatedAt)
+                            .from(Post::Table)
+                            .and_where(Expr::col(Post::Image).is_not_null())
+                            .to_owned(),
+                    )
+                    .map_err(|err| DbErr::Migration(err.to_string()))?
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Post::Table)
+                    .drop_column(Post::Image)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Post::Table)
+                    .add_column(string_null(Post::Image))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Only the first attachment of each post survives
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(Post::Table)
+                    .value(
+                        Post::Image,
+                        Expr::expr(SimpleExpr::SubQuery(
+                            None,
+                            Box::new(
+                                Query::select()
+                                    .column(PostAttachment::FileName)
+                                    .from(PostAttachment::Table)
+                                    .and_where(
+                                        Expr::col((PostAttachment::Table, PostAttachment::PostId))
+                                            .equals((Post::Table, Post::Id)),
+                                    )
+                                    .order_by(PostAttachment::Position, Order::Asc)
+                                    .limit(1)
+                                    .to_owned()
+                                    .into_sub_query_statement(),
+                            ),
+                        )),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(PostAttachment::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum PostAttachment {
+    Table,
+    Id,
+    Uuid,
+    PostId,
+    Position,
+    FileName,
+    AltText,
+    Width,
+    Height,
+    CreatedAt,
+}
@@ -0,0 +1,69 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName, Debug)]
+pub(crate) struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(AttachmentThumbnail::Table)
+                    .if_not_exists()
+                    .col(pk_auto(AttachmentThumbnail::Id))
+                    .col(integer(AttachmentThumbnail::AttachmentId))
+                    .col(integer(AttachmentThumbnail::Size))
+                    .col(string(AttachmentThumbnail::FileName))
+                    .col(integer(AttachmentThumbnail::Width))
+                    .col(integer(AttachmentThumbnail::Height))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-attachment_thumbnails-post_attachments-id")
+                            .from(
+                                AttachmentThumbnail::Table,
+                                AttachmentThumbnail::AttachmentId,
+                            )
+                            .to(PostAttachment::Table, PostAttachment::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-attachment_thumbnail-attachment_id-size")
+                    .table(AttachmentThumbnail::Table)
+                    .col(AttachmentThumbnail::AttachmentId)
+                    .col(AttachmentThumbnail::Size)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AttachmentThumbnail::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum AttachmentThumbnail {
+    Table,
+    Id,
+    AttachmentId,
+    Size,
+    FileName,
+    Width,
+    Height,
+}
+
+#[derive(DeriveIden)]
+enum PostAttachment {
+    Table,
+    Id,
+}
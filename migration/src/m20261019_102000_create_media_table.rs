@@ -0,0 +1,82 @@
+use sea_orm_migration::{prelude::*, schema::*, sea_query::UnionType};
+
+#[derive(DeriveMigrationName, Debug)]
+pub(crate) struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Media::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Media::Id))
+                    .col(string_uniq(Media::FileName))
+                    .col(integer(Media::RefCount).default(0))
+                    .col(
+                        timestamp_with_time_zone(Media::CreatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Files uploaded so far keep their names, each reference to them counts
+        let files = Query::select()
+            .column(PostAttachment::FileName)
+            .from(PostAttachment::Table)
+            .union(
+                UnionType::All,
+                Query::select()
+                    .column(AttachmentThumbnail::FileName)
+                    .from(AttachmentThumbnail::Table)
+                    .to_owned(),
+            )
+            .to_owned();
+        manager
+            .exec_stmt(
+                Query::insert()
+                    .into_table(Media::Table)
+                    .columns([Media::FileName, Media::RefCount])
+                    .select_from(
+                        Query::select()
+                            .column(Media::FileName)
+                            .expr(Expr::col(Media::FileName).count())
+                            .from_subquery(files, Alias::new("files"))
+                            .group_by_col(Media::FileName)
+                            .to_owned(),
+                    )
+                    .map_err(|err| DbErr::Migration(err.to_string()))?
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Media::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Media {
+    Table,
+    Id,
+    FileName,
+    RefCount,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum PostAttachment {
+    Table,
+    FileName,
+}
+
+#[derive(DeriveIden)]
+enum AttachmentThumbnail {
+    Table,
+    FileName,
+}
@@ -0,0 +1,69 @@
+use sea_orm_migration::prelude::{extension::postgres::Type, *};
+
+#[derive(DeriveMigrationName, Debug)]
+pub(crate) struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(PostVisibility::Enum)
+                    .values([
+                        PostVisibility::Public,
+                        PostVisibility::Unlisted,
+                        PostVisibility::Followers,
+                        PostVisibility::Private,
+                    ])
+                    .to_owned(),
+            )
+            .await?;
+
+        // Posts written before visibilities existed could be seen by everyone
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Post::Table)
+                    .add_column(
+                        ColumnDef::new(Post::Visibility)
+                            .custom(PostVisibility::Enum)
+                            .not_null()
+                            .default("public"),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Post::Table)
+                    .drop_column(Post::Visibility)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_type(Type::drop().name(PostVisibility::Enum).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Post {
+    Table,
+    Visibility,
+}
+
+#[derive(DeriveIden)]
+enum PostVisibility {
+    #[sea_orm(iden = "post_visibility")]
+    Enum,
+    Public,
+    Unlisted,
+    Followers,
+    Private,
+}
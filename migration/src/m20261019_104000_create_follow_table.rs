@@ -0,0 +1,71 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20250525_145126_create_user_table::User;
+
+#[derive(DeriveMigrationName, Debug)]
+pub(crate) struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Follow::Table)
+                    .if_not_exists()
+                    .col(integer(Follow::FollowerId))
+                    .col(integer(Follow::FolloweeId))
+                    .col(
+                        timestamp_with_time_zone(Follow::CreatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(Follow::FollowerId)
+                            .col(Follow::FolloweeId),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-follows-follower-users-id")
+                            .from(Follow::Table, Follow::FollowerId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-follows-followee-users-id")
+                            .from(Follow::Table, Follow::FolloweeId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Listing the followers of a user goes from the followee
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-follow-followee_id-follower_id")
+                    .table(Follow::Table)
+                    .col(Follow::FolloweeId)
+                    .col(Follow::FollowerId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Follow::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Follow {
+    Table,
+    FollowerId,
+    FolloweeId,
+    CreatedAt,
+}
@@ -0,0 +1,136 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::{m20220101_000001_create_table::Post, m20250525_145126_create_user_table::User};
+
+#[derive(DeriveMigrationName, Debug)]
+pub(crate) struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(BookmarkCollection::Table)
+                    .if_not_exists()
+                    .col(pk_auto(BookmarkCollection::Id))
+                    .col(uuid_uniq(BookmarkCollection::Uuid))
+                    .col(integer(BookmarkCollection::UserId))
+                    .col(string_len(BookmarkCollection::Name, 100))
+                    .col(boolean(BookmarkCollection::IsDefault).default(false))
+                    .col(
+                        timestamp_with_time_zone(BookmarkCollection::CreatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-bookmark_collections-users-id")
+                            .from(BookmarkCollection::Table, BookmarkCollection::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // The default collection can't be renamed, so its name keeps users to one of them
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-bookmark_collection-user_id-name")
+                    .table(BookmarkCollection::Table)
+                    .col(BookmarkCollection::UserId)
+                    .col(BookmarkCollection::Name)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(Bookmark::Table)
+                    .if_not_exists()
+                    .col(integer(Bookmark::CollectionId))
+                    .col(integer(Bookmark::PostId))
+                    .col(
+                        timestamp_with_time_zone(Bookmark::CreatedAt)
+                            .default(Expr::current_timestamp()),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(Bookmark::CollectionId)
+                            .col(Bookmark::PostId),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-bookmarks-bookmark_collections-id")
+                            .from(Bookmark::Table, Bookmark::CollectionId)
+                            .to(BookmarkCollection::Table, BookmarkCollection::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-bookmarks-posts-id")
+                            .from(Bookmark::Table, Bookmark::PostId)
+                            .to(Post::Table, Post::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Collections are listed newest bookmark first
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-bookmark-collection_id-created_at-post_id")
+                    .table(Bookmark::Table)
+                    .col(Bookmark::CollectionId)
+                    .col(Bookmark::CreatedAt)
+                    .col(Bookmark::PostId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Whether posts are bookmarked is looked up by post
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-bookmark-post_id")
+                    .table(Bookmark::Table)
+                    .col(Bookmark::PostId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Bookmark::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(BookmarkCollection::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum BookmarkCollection {
+    Table,
+    Id,
+    Uuid,
+    UserId,
+    Name,
+    IsDefault,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Bookmark {
+    Table,
+    CollectionId,
+    PostId,
+    CreatedAt,
+}
@@ -0,0 +1,90 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::m20220101_000001_create_table::Post;
+
+#[derive(DeriveMigrationName, Debug)]
+pub(crate) struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PostViewDaily::Table)
+                    .if_not_exists()
+                    .col(integer(PostViewDaily::PostId))
+                    .col(date(PostViewDaily::Day))
+                    .col(big_integer(PostViewDaily::Views).default(0))
+                    .col(big_integer(PostViewDaily::UniqueViewers).default(0))
+                    .primary_key(
+                        Index::create()
+                            .col(PostViewDaily::PostId)
+                            .col(PostViewDaily::Day),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-post_view_dailies-posts-id")
+                            .from(PostViewDaily::Table, PostViewDaily::PostId)
+                            .to(Post::Table, Post::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(PostReferrerDaily::Table)
+                    .if_not_exists()
+                    .col(integer(PostReferrerDaily::PostId))
+                    .col(date(PostReferrerDaily::Day))
+                    .col(string_len(PostReferrerDaily::Referrer, 255))
+                    .col(big_integer(PostReferrerDaily::Views).default(0))
+                    .primary_key(
+                        Index::create()
+                            .col(PostReferrerDaily::PostId)
+                            .col(PostReferrerDaily::Day)
+                            .col(PostReferrerDaily::Referrer),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-post_referrer_dailies-posts-id")
+                            .from(PostReferrerDaily::Table, PostReferrerDaily::PostId)
+                            .to(Post::Table, Post::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PostReferrerDaily::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(PostViewDaily::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum PostViewDaily {
+    Table,
+    PostId,
+    Day,
+    Views,
+    UniqueViewers,
+}
+
+#[derive(DeriveIden)]
+enum PostReferrerDaily {
+    Table,
+    PostId,
+    Day,
+    Referrer,
+    Views,
+}
@@ -1,4 +1,6 @@
 pub mod error;
 pub mod routes;
 pub(crate) mod schemas;
+pub mod storage;
+pub mod tasks;
 pub mod utils;
@@ -1,10 +1,15 @@
-use actix_web::{middleware::Logger, web, App, HttpServer};
-use actix_youtube::utils::app_state::AppState;
+use std::sync::Arc;
+
+use actix_multipart::{form::MultipartFormConfig, MultipartError};
+use actix_web::{error::PayloadError, middleware::Logger, web, App, HttpServer, ResponseError};
+use actix_youtube::utils::{api_response::ApiResponse, app_state::AppState, views::ViewCounter};
 use migration::{Migrator, MigratorTrait};
 use sea_orm::{Database, DatabaseConnection};
 
 use actix_youtube::error::MainError;
 use actix_youtube::routes;
+use actix_youtube::storage;
+use actix_youtube::tasks;
 use actix_youtube::utils;
 
 #[actix_web::main] // or #[tokio::main]
@@ -29,18 +34,73 @@ async fn main() -> Result<(), MainError> {
         message: err.to_string(),
     })?;
 
+    // Storage backend for uploaded files, chosen by config
+    let storage = storage::from_config().map_err(|err| MainError {
+        message: err.to_string(),
+    })?;
+
+    // Intervals of the background tasks, read up front so invalid ones fail at startup
+    utils::constants::get_purge_interval_secs();
+    utils::constants::get_publish_scheduler_interval_secs();
+    utils::constants::get_media_sweep_interval_secs();
+
+    // Removing soft deleted posts once they can no longer be restored
+    actix_web::rt::spawn(tasks::purge_posts::run(db.clone(), storage.clone()));
+    // Publishing scheduled posts once they are due
+    actix_web::rt::spawn(tasks::publish_scheduled::run(db.clone()));
+    // Caching the HTML of posts written before it was rendered on save
+    actix_web::rt::spawn(tasks::render_markdown::run(db.clone()));
+    // Reporting or removing stored files no attachment refers to
+    actix_web::rt::spawn(tasks::sweep_media::run(db.clone(), storage.clone()));
+    // Writing post views counted in memory to the daily counters
+    let views = Arc::new(ViewCounter::new());
+    actix_web::rt::spawn(tasks::flush_views::run(db.clone(), views.clone()));
+    // Views counted since the last flush are written once the server stopped
+    let (shutdown_db, shutdown_views) = (db.clone(), views.clone());
+
     // App state to use db connection to across all routes
     // Adding logger middleware using `wrap`
     HttpServer::new(move || {
         App::new()
             .app_data(web::Data::new(AppState {
                 db: db.clone(),
+                storage: storage.clone(),
+                views: views.clone(),
             }))
+            .app_data(
+                web::QueryConfig::default()
+                    .error_handler(|err, _| ApiResponse::new(400, err.to_string()).into()),
+            )
+            // Upload limits are checked while the form is read, before it reaches the disk
+            .app_data(
+                MultipartFormConfig::default()
+                    .total_limit(utils::constants::get_max_upload_size() as usize)
+                    .memory_limit(utils::constants::get_max_form_memory() as usize)
+                    .error_handler(|err, _| match err {
+                        MultipartError::Payload(PayloadError::Overflow) => ApiResponse::new(
+                            413,
+                            format!(
+                                "Upload too large, files can have at most {} bytes and the \
+                                 whole form {} bytes",
+                                utils::constants::get_max_file_size(),
+                                utils::constants::get_max_upload_size()
+                            ),
+                        )
+                        .into(),
+                        err => ApiResponse::new(err.status_code().as_u16(), err.to_string()).into(),
+                    }),
+            )
             .wrap(Logger::default())
             .configure(routes::home_routes::config)
             .configure(routes::auth_routes::config)
             .configure(routes::user_routes::config)
             .configure(routes::post_routes::config)
+            .configure(routes::comment_routes::config)
+            .configure(routes::tag_routes::config)
+            .configure(routes::bookmark_routes::config)
+            .configure(routes::media_routes::config)
+            .configure(routes::feed_routes::config)
+            .configure(routes::admin_routes::config)
     })
     .bind((address, port))
     .map_err(|err| MainError {
@@ -50,5 +110,9 @@ async fn main() -> Result<(), MainError> {
     .await
     .map_err(|err| MainError {
         message: err.to_string(),
-    })
+    })?;
+
+    tasks::flush_views::flush_views(&shutdown_db, &shutdown_views).await;
+
+    Ok(())
 }
@@ -0,0 +1,11 @@
+use actix_web::{middleware::from_fn, web};
+
+use super::{handlers::admin_handlers, middleware};
+
+pub fn config(config: &mut web::ServiceConfig) {
+    config.service(
+        web::scope("/admin")
+            .wrap(from_fn(middleware::auth_middleware::check_auth_middleware))
+            .service(admin_handlers::sweep_orphaned_media),
+    );
+}
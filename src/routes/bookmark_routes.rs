@@ -0,0 +1,16 @@
+use actix_web::{middleware::from_fn, web};
+
+use super::{handlers::bookmark_handlers, middleware};
+
+pub fn config(config: &mut web::ServiceConfig) {
+    config.service(
+        web::scope("/bookmarks")
+            .wrap(from_fn(middleware::auth_middleware::check_auth_middleware))
+            .service(bookmark_handlers::get_collections)
+            .service(bookmark_handlers::create_collection)
+            .service(bookmark_handlers::delete_collection)
+            .service(bookmark_handlers::get_bookmarked_posts)
+            .service(bookmark_handlers::bookmark_post)
+            .service(bookmark_handlers::unbookmark_post),
+    );
+}
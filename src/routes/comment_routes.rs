@@ -0,0 +1,21 @@
+use actix_web::{middleware::from_fn, web};
+
+use super::{handlers::comment_handlers, middleware};
+
+pub fn config(config: &mut web::ServiceConfig) {
+    config
+        .service(
+            // Secure Comments
+            web::scope("/secure/comment")
+                .wrap(from_fn(middleware::auth_middleware::check_auth_middleware))
+                .service(comment_handlers::update_comment)
+                .service(comment_handlers::delete_comment),
+        )
+        .service(
+            web::scope("/comment")
+                .wrap(from_fn(
+                    middleware::auth_middleware::optional_auth_middleware,
+                ))
+                .service(comment_handlers::get_comment_replies),
+        );
+}
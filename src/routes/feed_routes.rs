@@ -0,0 +1,13 @@
+use actix_web::web;
+
+use super::handlers::feed_handlers;
+
+pub fn config(config: &mut web::ServiceConfig) {
+    config.service(
+        web::scope("/feeds")
+            .service(feed_handlers::posts_atom)
+            .service(feed_handlers::posts_rss)
+            .service(feed_handlers::author_posts_atom)
+            .service(feed_handlers::author_posts_rss),
+    );
+}
@@ -0,0 +1,40 @@
+use actix_web::{post, web};
+use sea_orm::EntityTrait;
+
+use crate::{
+    schemas::media_schemas::MediaSweepQuery,
+    tasks::sweep_media::sweep_media,
+    utils::{api_response::ApiResponse, app_state, constants::get_admin_emails, jwt::Claims},
+};
+
+/// Reconcile the stored files with the database. Only reports orphaned files unless
+/// `dry_run=false` is given.
+#[post("media/sweep")]
+pub(crate) async fn sweep_orphaned_media(
+    app_state: web::Data<app_state::AppState>,
+    query: web::Query<MediaSweepQuery>,
+    claim: Claims,
+) -> Result<ApiResponse, ApiResponse> {
+    require_admin(&app_state, &claim).await?;
+
+    let report = sweep_media(
+        &app_state.db,
+        app_state.storage.clone(),
+        query.dry_run.unwrap_or(true),
+    )
+    .await?;
+    ApiResponse::serialize(200, &report)
+}
+
+/// Fail with 403 unless the user is listed in `ADMIN_EMAILS`. The email is looked up as it may
+/// have changed since the token was issued.
+async fn require_admin(app_state: &app_state::AppState, claim: &Claims) -> Result<(), ApiResponse> {
+    let user = entity::user::Entity::find_by_id(claim.id)
+        .one(&app_state.db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+    match user {
+        Some(user) if get_admin_emails().contains(&user.email.to_lowercase()) => Ok(()),
+        _ => Err(ApiResponse::new(403, "Admins only".to_string())),
+    }
+}
@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+
+use actix_web::{get, web};
+use chrono::{Duration, NaiveDate, Utc};
+use entity::{post_referrer_daily, post_view_daily};
+use sea_orm::{
+    sea_query::{Alias, Expr, Func, SimpleExpr},
+    ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect,
+};
+use uuid::Uuid;
+
+use super::post_handlers::find_own_post;
+use crate::{
+    schemas::analytics_schemas::{AnalyticsQuery, DailyViewsOut, PostAnalyticsOut, ReferrerOut},
+    utils::{api_response::ApiResponse, app_state, jwt::Claims},
+};
+
+/// Longest range of days analytics are given for at once
+const MAX_ANALYTICS_DAYS: i64 = 366;
+/// Number of referrers listed
+const MAX_REFERRERS: u64 = 20;
+
+/// Views of one of the authenticated user's posts per day, with the sites linking to it
+#[get("{post_uuid}/analytics")]
+pub(crate) async fn get_post_analytics(
+    app_state: web::Data<app_state::AppState>,
+    claim: Claims,
+    post_uuid: web::Path<Uuid>,
+    analytics_query: web::Query<AnalyticsQuery>,
+) -> Result<ApiResponse, ApiResponse> {
+    let db = &app_state.db;
+    let post = find_own_post(db, *post_uuid, claim.id).await?;
+
+    // Dates far off would overflow the arithmetic below or the database's date type
+    let valid_days = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()
+        ..=NaiveDate::from_ymd_opt(9999, 12, 31).unwrap();
+    if [analytics_query.from, analytics_query.to]
+        .into_iter()
+        .flatten()
+        .any(|day| !valid_days.contains(&day))
+    {
+        return Err(ApiResponse::new(
+            400,
+            "Days have to be between 1970-01-01 and 9999-12-31".to_string(),
+        ));
+    }
+    let to = analytics_query.to.unwrap_or(Utc::now().date_naive());
+    let from = analytics_query
+        .from
+        .unwrap_or_else(|| to - Duration::days(29));
+    if from > to {
+        return Err(ApiResponse::new(400, "from can't be after to".to_string()));
+    }
+    if (to - from).num_days() >= MAX_ANALYTICS_DAYS {
+        return Err(ApiResponse::new(
+            400,
+            format!(
+                "Analytics cover at most {} days at once",
+                MAX_ANALYTICS_DAYS
+            ),
+        ));
+    }
+
+    let mut daily: HashMap<NaiveDate, post_view_daily::Model> = post_view_daily::Entity::find()
+        .filter(post_view_daily::Column::PostId.eq(post.id))
+        .filter(post_view_daily::Column::Day.between(from, to))
+        .all(db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?
+        .into_iter()
+        .map(|views| (views.day, views))
+        .collect();
+    let days: Vec<DailyViewsOut> = from
+        .iter_days()
+        .take_while(|day| *day <= to)
+        .map(|day| {
+            let views = daily.remove(&day);
+            DailyViewsOut {
+                day,
+                views: views.as_ref().map_or(0, |views| views.views) as u64,
+                unique_viewers: views.as_ref().map_or(0, |views| views.unique_viewers) as u64,
+            }
+        })
+        .collect();
+
+    // `sum` of a bigint column is a numeric
+    let total: SimpleExpr = Func::cast_as(
+        Func::sum(Expr::col((
+            post_referrer_daily::Entity,
+            post_referrer_daily::Column::Views,
+        ))),
+        Alias::new("bigint"),
+    )
+    .into();
+    let referrers = post_referrer_daily::Entity::find()
+        .select_only()
+        .column(post_referrer_daily::Column::Referrer)
+        .column_as(total, "total")
+        .filter(post_referrer_daily::Column::PostId.eq(post.id))
+        .filter(post_referrer_daily::Column::Day.between(from, to))
+        .group_by(post_referrer_daily::Column::Referrer)
+        .order_by_desc(Expr::cust("total"))
+        .order_by_asc(post_referrer_daily::Column::Referrer)
+        .limit(MAX_REFERRERS)
+        .into_tuple::<(String, i64)>()
+        .all(db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?
+        .into_iter()
+        .map(|(referrer, views)| ReferrerOut {
+            referrer: Some(referrer).filter(|referrer| !referrer.is_empty()),
+            views: views as u64,
+        })
+        .collect();
+
+    ApiResponse::serialize(
+        200,
+        &PostAnalyticsOut {
+            post_uuid: post.uuid,
+            from,
+            to,
+            views: days.iter().map(|day| day.views).sum(),
+            daily_unique_viewers_sum: days.iter().map(|day| day.unique_viewers).sum(),
+            days,
+            referrers,
+        },
+    )
+}
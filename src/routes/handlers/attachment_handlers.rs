@@ -0,0 +1,370 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use actix_web::{delete, patch, put, web};
+use chrono::{FixedOffset, Utc};
+use entity::{attachment_thumbnail, post, post_attachment};
+use sea_orm::{
+    sea_query::{Expr, SimpleExpr},
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DbErr, EntityTrait, IntoActiveModel,
+    ModelTrait, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Set, TransactionTrait,
+};
+use uuid::Uuid;
+
+use super::post_handlers::{find_own_post, lock_post};
+use crate::{
+    schemas::attachment_schemas::{AttachmentOut, ReorderAttachments, UpdateAttachment},
+    storage::Storage,
+    utils::{
+        api_response::ApiResponse,
+        app_state,
+        jwt::Claims,
+        media::{acquire_media, release_media, remove_unreferenced},
+        media_urls::MediaAccess,
+        post_visibility::{is_publicly_viewable, publicly_viewable},
+        uploads::{put_images, ProcessedImage},
+    },
+};
+
+/// Most images a single post may have
+pub(crate) const MAX_ATTACHMENTS: usize = 10;
+
+/// Change the order of the attachments of a post. All of them have to be listed.
+#[put("{post_uuid}/attachments/order")]
+pub(crate) async fn reorder_attachments(
+    app_state: web::Data<app_state::AppState>,
+    claim: Claims,
+    post_uuid: web::Path<Uuid>,
+    order_json: web::Json<ReorderAttachments>,
+) -> Result<ApiResponse, ApiResponse> {
+    let post = find_own_post(&app_state.db, *post_uuid, claim.id).await?;
+
+    let txn = app_state
+        .db
+        .begin()
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+    let attachments = post_attachment::Entity::find()
+        .filter(post_attachment::Column::PostId.eq(post.id))
+        .lock_exclusive()
+        .all(&txn)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+    let current: HashSet<Uuid> = attachments
+        .iter()
+        .map(|attachment| attachment.uuid)
+        .collect();
+    let requested: HashSet<Uuid> = order_json.attachments.iter().copied().collect();
+    if requested.len() != order_json.attachments.len() || requested != current {
+        return Err(ApiResponse::new(
+            400,
+            "The new order has to list every attachment of the post exactly once".to_string(),
+        ));
+    }
+
+    let mut attachments: HashMap<Uuid, post_attachment::Model> = attachments
+        .into_iter()
+        .map(|attachment| (attachment.uuid, attachment))
+        .collect();
+    for (position, uuid) in order_json.attachments.iter().enumerate() {
+        let Some(attachment) = attachments.remove(uuid) else {
+            continue;
+        };
+        if attachment.position == position as i32 {
+            continue;
+        }
+        let mut attachment_entity = attachment.into_active_model();
+        attachment_entity.position = Set(position as i32);
+        attachment_entity
+            .update(&txn)
+            .await
+            .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+    }
+    txn.commit()
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+    let attachments = post_attachments(&app_state, &[post.id], Some(claim.id))
+        .await?
+        .remove(&post.id)
+        .unwrap_or_default();
+
+    ApiResponse::serialize(200, &attachments)
+}
+
+#[patch("{post_uuid}/attachments/{attachment_uuid}")]
+pub(crate) async fn update_attachment(
+    app_state: web::Data<app_state::AppState>,
+    claim: Claims,
+    path: web::Path<(Uuid, Uuid)>,
+    attachment_json: web::Json<UpdateAttachment>,
+) -> Result<ApiResponse, ApiResponse> {
+    let (post_uuid, attachment_uuid) = path.into_inner();
+    let post = find_own_post(&app_state.db, post_uuid, claim.id).await?;
+    let attachment = find_attachment(&app_state.db, post.id, attachment_uuid).await?;
+
+    let mut attachment_entity = attachment.into_active_model();
+    attachment_entity.alt_text = Set(normalize_alt_text(attachment_json.alt_text.as_deref()));
+    let attachment = attachment_entity
+        .update(&app_state.db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+    let thumbnails = attachment
+        .find_related(attachment_thumbnail::Entity)
+        .order_by_asc(attachment_thumbnail::Column::Size)
+        .all(&app_state.db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+    let access = MediaAccess::for_post(is_publicly_viewable(&post), Some(claim.id));
+    let attachment_out =
+        AttachmentOut::new(attachment, thumbnails, app_state.storage.as_ref(), access);
+
+    ApiResponse::serialize(200, &attachment_out)
+}
+
+/// Remove a single attachment. The ones after it move up.
+#[delete("{post_uuid}/attachments/{attachment_uuid}")]
+pub(crate) async fn delete_attachment(
+    app_state: web::Data<app_state::AppState>,
+    claim: Claims,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<ApiResponse, ApiResponse> {
+    let (post_uuid, attachment_uuid) = path.into_inner();
+    let post = find_own_post(&app_state.db, post_uuid, claim.id).await?;
+
+    let txn = app_state
+        .db
+        .begin()
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+    // Taken before the rows of the files, in the same order as uploads to the post do
+    lock_post(&txn, post.id).await?;
+    let attachment = find_attachment(&txn, post.id, attachment_uuid).await?;
+    let file_names = attachment_files(&txn, post_attachment::Column::Id.eq(attachment.id))
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+    release_media(&txn, file_names.iter().map(String::as_str))
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+    let position = attachment.position;
+    attachment
+        .delete(&txn)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+    post_attachment::Entity::update_many()
+        .col_expr(
+            post_attachment::Column::Position,
+            Expr::col(post_attachment::Column::Position).sub(1),
+        )
+        .filter(post_attachment::Column::PostId.eq(post.id))
+        .filter(post_attachment::Column::Position.gt(position))
+        .exec(&txn)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+    remove_unreferenced(
+        &txn,
+        &app_state.storage,
+        file_names.iter().map(String::as_str),
+    )
+    .await
+    .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+    txn.commit()
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+    let attachments = post_attachments(&app_state, &[post.id], Some(claim.id))
+        .await?
+        .remove(&post.id)
+        .unwrap_or_default();
+
+    ApiResponse::serialize(200, &attachments)
+}
+
+/// Put `images` into storage and attach them to the post with `post_id`, after the ones it
+/// already has, counting the references to their files. `alt_texts` belong to the images in the
+/// same order. Meant to be called in a transaction finished by
+/// [`commit_upload`](crate::utils::media::commit_upload), which cleans up after a failure.
+pub(crate) async fn attach_images<C: ConnectionTrait>(
+    db: &C,
+    storage: &Arc<dyn Storage>,
+    post_id: i32,
+    images: &Arc<Vec<ProcessedImage>>,
+    alt_texts: &[&str],
+) -> Result<(), ApiResponse> {
+    if images.is_empty() {
+        return Ok(());
+    }
+
+    // Concurrent uploads to the post wait for each other, so together they stay within the limit
+    lock_post(db, post_id).await?;
+    let attached = post_attachment::Entity::find()
+        .filter(post_attachment::Column::PostId.eq(post_id))
+        .count(db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))? as usize;
+    if attached + images.len() > MAX_ATTACHMENTS {
+        return Err(ApiResponse::new(
+            400,
+            format!("A post can have at most {} images", MAX_ATTACHMENTS),
+        ));
+    }
+
+    // Referenced before they are put, so they can't be removed as unreferenced in between
+    acquire_media(db, images.iter().flat_map(ProcessedImage::file_names))
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+    web::block({
+        let (storage, images) = (storage.clone(), images.clone());
+        move || put_images(storage.as_ref(), &images)
+    })
+    .await
+    .map_err(|err| ApiResponse::new(500, err.to_string()))??;
+
+    let now = Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap());
+    for (index, image) in images.iter().enumerate() {
+        let attachment = post_attachment::ActiveModel {
+            uuid: Set(Uuid::new_v4()),
+            post_id: Set(post_id),
+            position: Set((attached + index) as i32),
+            file_name: Set(image.file_name.clone()),
+            alt_text: Set(normalize_alt_text(alt_texts.get(index).copied())),
+            width: Set(Some(image.width)),
+            height: Set(Some(image.height)),
+            created_at: Set(now),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+        if image.thumbnails.is_empty() {
+            continue;
+        }
+        attachment_thumbnail::Entity::insert_many(image.thumbnails.iter().map(|thumbnail| {
+            attachment_thumbnail::ActiveModel {
+                attachment_id: Set(attachment.id),
+                size: Set(thumbnail.size),
+                file_name: Set(thumbnail.file_name.clone()),
+                width: Set(thumbnail.width),
+                height: Set(thumbnail.height),
+                ..Default::default()
+            }
+        }))
+        .exec(db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+    }
+
+    Ok(())
+}
+
+/// Remove every attachment of the post with `post_id`. Returns the names of their files, to be
+/// passed to [`remove_unreferenced`] once the post's new attachments are counted.
+pub(crate) async fn detach_all<C: ConnectionTrait>(
+    db: &C,
+    post_id: i32,
+) -> Result<Vec<String>, ApiResponse> {
+    // Taken before the rows of the files, in the same order as uploads to the post do
+    lock_post(db, post_id).await?;
+    let file_names = attachment_files(db, post_attachment::Column::PostId.eq(post_id))
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+    post_attachment::Entity::delete_many()
+        .filter(post_attachment::Column::PostId.eq(post_id))
+        .exec(db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+    release_media(db, file_names.iter().map(String::as_str))
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+    Ok(file_names)
+}
+
+/// Names of the files of the attachments matching `condition`, thumbnails included
+pub async fn attachment_files<C: ConnectionTrait>(
+    db: &C,
+    condition: SimpleExpr,
+) -> Result<Vec<String>, DbErr> {
+    let attachments = post_attachment::Entity::find()
+        .filter(condition)
+        .find_with_related(attachment_thumbnail::Entity)
+        .all(db)
+        .await?;
+
+    Ok(attachments
+        .into_iter()
+        .flat_map(|(attachment, thumbnails)| {
+            std::iter::once(attachment.file_name)
+                .chain(thumbnails.into_iter().map(|thumbnail| thumbnail.file_name))
+        })
+        .collect())
+}
+
+/// Attachments of each of `post_ids`, in their order, as shown to `viewer`
+pub(crate) async fn post_attachments(
+    app_state: &app_state::AppState,
+    post_ids: &[i32],
+    viewer: Option<i32>,
+) -> Result<HashMap<i32, Vec<AttachmentOut>>, ApiResponse> {
+    let public: HashSet<i32> = post::Entity::find()
+        .select_only()
+        .column(post::Column::Id)
+        .filter(post::Column::Id.is_in(post_ids.iter().copied()))
+        .filter(publicly_viewable())
+        .into_tuple::<i32>()
+        .all(&app_state.db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?
+        .into_iter()
+        .collect();
+    let attachments = post_attachment::Entity::find()
+        .filter(post_attachment::Column::PostId.is_in(post_ids.iter().copied()))
+        .order_by_asc(post_attachment::Column::Position)
+        .find_with_related(attachment_thumbnail::Entity)
+        .order_by_asc(attachment_thumbnail::Column::Size)
+        .all(&app_state.db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+    let mut by_post: HashMap<i32, Vec<AttachmentOut>> = HashMap::new();
+    for (attachment, thumbnails) in attachments {
+        let post_id = attachment.post_id;
+        let access = MediaAccess::for_post(public.contains(&post_id), viewer);
+        by_post.entry(post_id).or_default().push(AttachmentOut::new(
+            attachment,
+            thumbnails,
+            app_state.storage.as_ref(),
+            access,
+        ));
+    }
+
+    Ok(by_post)
+}
+
+async fn find_attachment<C: ConnectionTrait>(
+    db: &C,
+    post_id: i32,
+    attachment_uuid: Uuid,
+) -> Result<post_attachment::Model, ApiResponse> {
+    post_attachment::Entity::find()
+        .filter(post_attachment::Column::Uuid.eq(attachment_uuid))
+        .filter(post_attachment::Column::PostId.eq(post_id))
+        .one(db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?
+        .ok_or(ApiResponse::new(404, "No attachment found".to_string()))
+}
+
+/// Blank alt texts are stored as missing
+fn normalize_alt_text(alt_text: Option<&str>) -> Option<String> {
+    alt_text
+        .map(str::trim)
+        .filter(|alt_text| !alt_text.is_empty())
+        .map(str::to_string)
+}
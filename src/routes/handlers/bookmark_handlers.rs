@@ -0,0 +1,428 @@
+use std::collections::{HashMap, HashSet};
+
+use actix_web::{delete, get, post, put, web};
+use chrono::{FixedOffset, Utc};
+use entity::{bookmark, bookmark_collection, post};
+use sea_orm::{
+    sea_query::{Expr, OnConflict, Query, SelectStatement},
+    ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, QuerySelect, Set,
+    TryInsertResult,
+};
+use uuid::Uuid;
+
+use super::post_handlers::enrich_posts;
+use crate::{
+    schemas::{
+        bookmark_schemas::{
+            BookmarkCollectionOut, BookmarkListQuery, BookmarkOut, BookmarkQuery,
+            CreateBookmarkCollection,
+        },
+        pagination_schemas::{Page, PageQuery},
+        post_schemas::PostOut,
+    },
+    utils::{
+        api_response::ApiResponse,
+        app_state,
+        jwt::Claims,
+        pagination::{paginate, SortKey},
+        post_visibility::{find_viewable_post, viewable_by},
+    },
+};
+
+/// Name of the collection every user has, posts go there unless another one is given
+const DEFAULT_COLLECTION_NAME: &str = "Saved";
+const MAX_COLLECTION_NAME_LENGTH: usize = 100;
+
+#[get("collections")]
+pub(crate) async fn get_collections(
+    app_state: web::Data<app_state::AppState>,
+    claim: Claims,
+) -> Result<ApiResponse, ApiResponse> {
+    default_collection(&app_state.db, claim.id).await?;
+    let collections = bookmark_collection::Entity::find()
+        .filter(bookmark_collection::Column::UserId.eq(claim.id))
+        .order_by_desc(bookmark_collection::Column::IsDefault)
+        .order_by_asc(bookmark_collection::Column::Name)
+        .all(&app_state.db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+    let collections = collections_out(&app_state.db, collections, claim.id).await?;
+
+    ApiResponse::serialize(200, &collections)
+}
+
+#[post("collections")]
+pub(crate) async fn create_collection(
+    app_state: web::Data<app_state::AppState>,
+    claim: Claims,
+    collection_json: web::Json<CreateBookmarkCollection>,
+) -> Result<ApiResponse, ApiResponse> {
+    let name = collection_json.name.trim();
+    if name.is_empty() || name.chars().count() > MAX_COLLECTION_NAME_LENGTH {
+        return Err(ApiResponse::new(
+            400,
+            format!(
+                "Collection name has to be between 1 and {} characters long",
+                MAX_COLLECTION_NAME_LENGTH
+            ),
+        ));
+    }
+    // The default collection claims its name first
+    default_collection(&app_state.db, claim.id).await?;
+
+    let inserted = bookmark_collection::Entity::insert(bookmark_collection::ActiveModel {
+        uuid: Set(Uuid::new_v4()),
+        user_id: Set(claim.id),
+        name: Set(name.to_string()),
+        is_default: Set(false),
+        created_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
+        ..Default::default()
+    })
+    .on_conflict(
+        OnConflict::columns([
+            bookmark_collection::Column::UserId,
+            bookmark_collection::Column::Name,
+        ])
+        .do_nothing()
+        .to_owned(),
+    )
+    .do_nothing()
+    .exec(&app_state.db)
+    .await
+    .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+    let TryInsertResult::Inserted(inserted) = inserted else {
+        return Err(ApiResponse::new(
+            409,
+            "A collection with this name exists already".to_string(),
+        ));
+    };
+    let collection = bookmark_collection::Entity::find_by_id(inserted.last_insert_id)
+        .one(&app_state.db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?
+        .ok_or(ApiResponse::new(
+            500,
+            "Collection was not created".to_string(),
+        ))?;
+
+    let collection_out = collections_out(&app_state.db, vec![collection], claim.id)
+        .await?
+        .pop()
+        .ok_or(ApiResponse::new(
+            500,
+            "Collection was not created".to_string(),
+        ))?;
+
+    ApiResponse::serialize(201, &collection_out)
+}
+
+/// Delete a collection with its bookmarks. The default collection stays.
+#[delete("collections/{collection_uuid}")]
+pub(crate) async fn delete_collection(
+    app_state: web::Data<app_state::AppState>,
+    claim: Claims,
+    collection_uuid: web::Path<Uuid>,
+) -> Result<ApiResponse, ApiResponse> {
+    let collection = find_collection(&app_state.db, claim.id, Some(*collection_uuid)).await?;
+    if collection.is_default {
+        return Err(ApiResponse::new(
+            400,
+            "The default collection can't be deleted".to_string(),
+        ));
+    }
+
+    bookmark_collection::Entity::delete_by_id(collection.id)
+        .exec(&app_state.db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+    Ok(ApiResponse::json(200, "Collection deleted".to_string()))
+}
+
+/// Posts of a collection, newest bookmark first. Bookmarked posts the user may no longer see are
+/// left out, but kept in case they become visible again.
+#[get("posts")]
+pub(crate) async fn get_bookmarked_posts(
+    app_state: web::Data<app_state::AppState>,
+    claim: Claims,
+    list_query: web::Query<BookmarkListQuery>,
+) -> Result<ApiResponse, ApiResponse> {
+    let db = &app_state.db;
+    let collection = find_collection(db, claim.id, list_query.collection).await?;
+
+    let select = bookmark::Entity::find()
+        .filter(bookmark::Column::CollectionId.eq(collection.id))
+        .filter(bookmark::Column::PostId.in_subquery(viewable_post_ids(claim.id)));
+    let sort = SortKey {
+        name: "bookmarked_at",
+        key: Expr::col((bookmark::Entity, bookmark::Column::CreatedAt)).into(),
+        id: Expr::col((bookmark::Entity, bookmark::Column::PostId)).into(),
+        descending: true,
+    };
+    let page = PageQuery {
+        limit: list_query.limit,
+        cursor: list_query.cursor.clone(),
+    };
+    let bookmarks = paginate(db, select, &sort, &page, |bookmark| {
+        (bookmark.created_at, bookmark.post_id)
+    })
+    .await?;
+
+    let mut posts: HashMap<i32, post::Model> = post::Entity::find()
+        .filter(post::Column::Id.is_in(bookmarks.data.iter().map(|bookmark| bookmark.post_id)))
+        .all(db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?
+        .into_iter()
+        .map(|post| (post.id, post))
+        .collect();
+    let mut data: Vec<PostOut> = bookmarks
+        .data
+        .iter()
+        .filter_map(|bookmark| posts.remove(&bookmark.post_id))
+        .map(PostOut::from)
+        .collect();
+    enrich_posts(&app_state, &mut data, Some(claim.id)).await?;
+
+    ApiResponse::serialize(
+        200,
+        &Page {
+            data,
+            next_cursor: bookmarks.next_cursor,
+            prev_cursor: bookmarks.prev_cursor,
+        },
+    )
+}
+
+/// Bookmark a post into the given collection, the default one if missing
+#[put("posts/{post_uuid}")]
+pub(crate) async fn bookmark_post(
+    app_state: web::Data<app_state::AppState>,
+    claim: Claims,
+    post_uuid: web::Path<Uuid>,
+    bookmark_query: web::Query<BookmarkQuery>,
+) -> Result<ApiResponse, ApiResponse> {
+    let post = find_viewable_post(&app_state.db, *post_uuid, Some(claim.id)).await?;
+    let collection = find_collection(&app_state.db, claim.id, bookmark_query.collection).await?;
+
+    // Bookmarking twice is a no-op
+    bookmark::Entity::insert(bookmark::ActiveModel {
+        collection_id: Set(collection.id),
+        post_id: Set(post.id),
+        created_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
+    })
+    .on_conflict(
+        OnConflict::columns([bookmark::Column::CollectionId, bookmark::Column::PostId])
+            .do_nothing()
+            .to_owned(),
+    )
+    .do_nothing()
+    .exec(&app_state.db)
+    .await
+    .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+    ApiResponse::serialize(200, &bookmark_out(&app_state.db, claim.id, post).await?)
+}
+
+/// Remove a post from the given collection, or from all collections if none is given
+#[delete("posts/{post_uuid}")]
+pub(crate) async fn unbookmark_post(
+    app_state: web::Data<app_state::AppState>,
+    claim: Claims,
+    post_uuid: web::Path<Uuid>,
+    bookmark_query: web::Query<BookmarkQuery>,
+) -> Result<ApiResponse, ApiResponse> {
+    // Posts which can't be seen anymore can still be removed from one's bookmarks
+    let post = post::Entity::find()
+        .filter(post::Column::Uuid.eq(*post_uuid))
+        .one(&app_state.db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?
+        .ok_or(ApiResponse::new(404, "No post found".to_string()))?;
+
+    let collections = match bookmark_query.collection {
+        Some(collection_uuid) => {
+            let collection =
+                find_collection(&app_state.db, claim.id, Some(collection_uuid)).await?;
+            bookmark::Column::CollectionId.eq(collection.id)
+        },
+        None => bookmark::Column::CollectionId.in_subquery(collections_of(claim.id)),
+    };
+    bookmark::Entity::delete_many()
+        .filter(bookmark::Column::PostId.eq(post.id))
+        .filter(collections)
+        .exec(&app_state.db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+    ApiResponse::serialize(200, &bookmark_out(&app_state.db, claim.id, post).await?)
+}
+
+/// Ids of `post_ids` bookmarked by `user_id` in any of their collections
+pub(crate) async fn bookmarked_posts(
+    db: &DatabaseConnection,
+    post_ids: &[i32],
+    user_id: i32,
+) -> Result<HashSet<i32>, ApiResponse> {
+    let bookmarked = bookmark::Entity::find()
+        .select_only()
+        .column(bookmark::Column::PostId)
+        .distinct()
+        .filter(bookmark::Column::PostId.is_in(post_ids.to_vec()))
+        .filter(bookmark::Column::CollectionId.in_subquery(collections_of(user_id)))
+        .into_tuple::<i32>()
+        .all(db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+    Ok(bookmarked.into_iter().collect())
+}
+
+/// The collection of `user_id` with `collection_uuid`, or their default collection if `None`
+async fn find_collection(
+    db: &DatabaseConnection,
+    user_id: i32,
+    collection_uuid: Option<Uuid>,
+) -> Result<bookmark_collection::Model, ApiResponse> {
+    let Some(collection_uuid) = collection_uuid else {
+        return default_collection(db, user_id).await;
+    };
+    // Collections of other users are reported as unknown
+    bookmark_collection::Entity::find()
+        .filter(bookmark_collection::Column::Uuid.eq(collection_uuid))
+        .filter(bookmark_collection::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?
+        .ok_or(ApiResponse::new(
+            404,
+            "No bookmark collection found".to_string(),
+        ))
+}
+
+/// The default collection of `user_id`, created the first time it's needed
+async fn default_collection(
+    db: &DatabaseConnection,
+    user_id: i32,
+) -> Result<bookmark_collection::Model, ApiResponse> {
+    let find = || {
+        bookmark_collection::Entity::find()
+            .filter(bookmark_collection::Column::UserId.eq(user_id))
+            .filter(bookmark_collection::Column::IsDefault.eq(true))
+            .one(db)
+    };
+    if let Some(collection) = find()
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?
+    {
+        return Ok(collection);
+    }
+
+    // Concurrent requests may create it at the same time, the name keeps it unique
+    bookmark_collection::Entity::insert(bookmark_collection::ActiveModel {
+        uuid: Set(Uuid::new_v4()),
+        user_id: Set(user_id),
+        name: Set(DEFAULT_COLLECTION_NAME.to_string()),
+        is_default: Set(true),
+        created_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
+        ..Default::default()
+    })
+    .on_conflict(
+        OnConflict::columns([
+            bookmark_collection::Column::UserId,
+            bookmark_collection::Column::Name,
+        ])
+        .do_nothing()
+        .to_owned(),
+    )
+    .do_nothing()
+    .exec(db)
+    .await
+    .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+    find()
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?
+        .ok_or(ApiResponse::new(
+            500,
+            "Default collection was not created".to_string(),
+        ))
+}
+
+/// Output form of `collections`, with the number of posts `user_id` may see in each
+async fn collections_out(
+    db: &DatabaseConnection,
+    collections: Vec<bookmark_collection::Model>,
+    user_id: i32,
+) -> Result<Vec<BookmarkCollectionOut>, ApiResponse> {
+    let post_counts: HashMap<i32, i64> = bookmark::Entity::find()
+        .select_only()
+        .column(bookmark::Column::CollectionId)
+        .column_as(bookmark::Column::PostId.count(), "count")
+        .filter(
+            bookmark::Column::CollectionId
+                .is_in(collections.iter().map(|collection| collection.id)),
+        )
+        .filter(bookmark::Column::PostId.in_subquery(viewable_post_ids(user_id)))
+        .group_by(bookmark::Column::CollectionId)
+        .into_tuple::<(i32, i64)>()
+        .all(db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?
+        .into_iter()
+        .collect();
+
+    Ok(collections
+        .into_iter()
+        .map(|collection| BookmarkCollectionOut {
+            post_count: post_counts.get(&collection.id).copied().unwrap_or(0) as u64,
+            uuid: collection.uuid,
+            name: collection.name,
+            is_default: collection.is_default,
+            created_at: collection.created_at,
+        })
+        .collect())
+}
+
+async fn bookmark_out(
+    db: &DatabaseConnection,
+    user_id: i32,
+    post: post::Model,
+) -> Result<BookmarkOut, ApiResponse> {
+    let collections = bookmark_collection::Entity::find()
+        .select_only()
+        .column(bookmark_collection::Column::Uuid)
+        .inner_join(bookmark::Entity)
+        .filter(bookmark_collection::Column::UserId.eq(user_id))
+        .filter(bookmark::Column::PostId.eq(post.id))
+        .order_by_desc(bookmark_collection::Column::IsDefault)
+        .order_by_asc(bookmark_collection::Column::Name)
+        .into_tuple::<Uuid>()
+        .all(db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+    Ok(BookmarkOut {
+        post_uuid: post.uuid,
+        collections,
+    })
+}
+
+/// Ids of the collections of `user_id`
+fn collections_of(user_id: i32) -> SelectStatement {
+    Query::select()
+        .column(bookmark_collection::Column::Id)
+        .from(bookmark_collection::Entity)
+        .and_where(bookmark_collection::Column::UserId.eq(user_id))
+        .to_owned()
+}
+
+/// Ids of the posts `viewer` may open
+fn viewable_post_ids(viewer: i32) -> SelectStatement {
+    Query::select()
+        .column(post::Column::Id)
+        .from(post::Entity)
+        .cond_where(viewable_by(viewer))
+        .to_owned()
+}
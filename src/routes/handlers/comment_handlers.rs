@@ -0,0 +1,383 @@
+use std::collections::HashMap;
+
+use actix_web::{delete, get, post, put, web};
+use chrono::{FixedOffset, Utc};
+use entity::comment;
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait,
+    IntoActiveModel, ModelTrait, PaginatorTrait, QueryFilter, QuerySelect, Set, TransactionTrait,
+};
+use uuid::Uuid;
+
+use crate::{
+    schemas::{
+        comment_schemas::{CommentOut, CreateComment, UpdateComment},
+        pagination_schemas::{Page, PageQuery},
+        user_schemas::PublicUserOut,
+    },
+    utils::{
+        api_response::ApiResponse,
+        app_state,
+        jwt::Claims,
+        pagination::{paginate, SortKey},
+        post_visibility::find_viewable_post,
+    },
+};
+
+const MAX_COMMENT_LENGTH: usize = 10_000;
+
+#[post("{post_uuid}/comments")]
+pub(crate) async fn create_comment(
+    app_state: web::Data<app_state::AppState>,
+    claim: Claims,
+    post_uuid: web::Path<Uuid>,
+    comment_json: web::Json<CreateComment>,
+) -> Result<ApiResponse, ApiResponse> {
+    let post = find_viewable_post(&app_state.db, *post_uuid, Some(claim.id)).await?;
+    let text = validate_text(&comment_json.text)?;
+
+    let txn = app_state
+        .db
+        .begin()
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+    let parent_id = match comment_json.parent_uuid {
+        Some(parent_uuid) => {
+            // Deleting the parent waits for the reply, so it is kept for it
+            let parent = comment::Entity::find()
+                .filter(comment::Column::Uuid.eq(parent_uuid))
+                .lock_shared()
+                .one(&txn)
+                .await
+                .map_err(|err| ApiResponse::new(500, err.to_string()))?
+                .ok_or(ApiResponse::new(404, "No comment found".to_string()))?;
+            if parent.post_id != post.id {
+                return Err(ApiResponse::new(
+                    400,
+                    "Parent comment belongs to another post".to_string(),
+                ));
+            }
+            if parent.deleted_at.is_some() {
+                return Err(ApiResponse::new(
+                    410,
+                    "Parent comment has been deleted".to_string(),
+                ));
+            }
+            Some(parent.id)
+        },
+        None => None,
+    };
+
+    let new_comment = comment::ActiveModel {
+        uuid: Set(Uuid::new_v4()),
+        post_id: Set(post.id),
+        user_id: Set(claim.id),
+        parent_id: Set(parent_id),
+        text: Set(text),
+        created_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await
+    .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+    txn.commit()
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+    let comment_out = comments_out(&app_state.db, vec![new_comment])
+        .await?
+        .pop()
+        .ok_or(ApiResponse::new(500, "Comment was not created".to_string()))?;
+
+    ApiResponse::serialize(201, &comment_out)
+}
+
+#[get("{post_uuid}/comments")]
+pub(crate) async fn get_post_comments(
+    app_state: web::Data<app_state::AppState>,
+    claim: Option<Claims>,
+    post_uuid: web::Path<Uuid>,
+    page: web::Query<PageQuery>,
+) -> Result<ApiResponse, ApiResponse> {
+    let viewer = claim.map(|claim| claim.id);
+    let post = find_viewable_post(&app_state.db, *post_uuid, viewer).await?;
+
+    let select = comment::Entity::find()
+        .filter(comment::Column::PostId.eq(post.id))
+        .filter(comment::Column::ParentId.is_null());
+    let comments = paginate(&app_state.db, select, &oldest_first(), &page, |comment| {
+        (comment.created_at, comment.id)
+    })
+    .await?;
+
+    let prev_cursor = comments.prev_cursor.clone();
+    let next_cursor = comments.next_cursor.clone();
+    let data = comments_out(&app_state.db, comments.data).await?;
+
+    ApiResponse::serialize(
+        200,
+        &Page {
+            data,
+            next_cursor,
+            prev_cursor,
+        },
+    )
+}
+
+#[get("{comment_uuid}/replies")]
+pub(crate) async fn get_comment_replies(
+    app_state: web::Data<app_state::AppState>,
+    claim: Option<Claims>,
+    comment_uuid: web::Path<Uuid>,
+    page: web::Query<PageQuery>,
+) -> Result<ApiResponse, ApiResponse> {
+    let comment = find_comment(&app_state.db, *comment_uuid).await?;
+    check_post_viewable(&app_state.db, &comment, claim.map(|claim| claim.id)).await?;
+
+    let select = comment::Entity::find().filter(comment::Column::ParentId.eq(comment.id));
+    let replies = paginate(&app_state.db, select, &oldest_first(), &page, |comment| {
+        (comment.created_at, comment.id)
+    })
+    .await?;
+
+    let prev_cursor = replies.prev_cursor.clone();
+    let next_cursor = replies.next_cursor.clone();
+    let data = comments_out(&app_state.db, replies.data).await?;
+
+    ApiResponse::serialize(
+        200,
+        &Page {
+            data,
+            next_cursor,
+            prev_cursor,
+        },
+    )
+}
+
+#[put("{comment_uuid}")]
+pub(crate) async fn update_comment(
+    app_state: web::Data<app_state::AppState>,
+    claim: Claims,
+    comment_uuid: web::Path<Uuid>,
+    comment_json: web::Json<UpdateComment>,
+) -> Result<ApiResponse, ApiResponse> {
+    let comment = find_comment(&app_state.db, *comment_uuid).await?;
+    check_post_viewable(&app_state.db, &comment, Some(claim.id)).await?;
+    if comment.user_id != claim.id {
+        return Err(ApiResponse::new(
+            403,
+            "You are not allowed to edit this comment".to_string(),
+        ));
+    }
+    if comment.deleted_at.is_some() {
+        return Err(ApiResponse::new(
+            410,
+            "Comment has been deleted".to_string(),
+        ));
+    }
+
+    let mut comment_entity = comment.into_active_model();
+    comment_entity.text = Set(validate_text(&comment_json.text)?);
+    comment_entity.updated_at = Set(Some(
+        Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap()),
+    ));
+    let updated_comment = comment_entity
+        .update(&app_state.db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+    let comment_out = comments_out(&app_state.db, vec![updated_comment])
+        .await?
+        .pop()
+        .ok_or(ApiResponse::new(404, "No comment found".to_string()))?;
+
+    ApiResponse::serialize(200, &comment_out)
+}
+
+/// Delete a comment. The author of the comment and the author of the post may do so.
+#[delete("{comment_uuid}")]
+pub(crate) async fn delete_comment(
+    app_state: web::Data<app_state::AppState>,
+    claim: Claims,
+    comment_uuid: web::Path<Uuid>,
+) -> Result<ApiResponse, ApiResponse> {
+    let comment = find_comment(&app_state.db, *comment_uuid).await?;
+    let post = check_post_viewable(&app_state.db, &comment, Some(claim.id)).await?;
+    if comment.user_id != claim.id && post.user_id != claim.id {
+        return Err(ApiResponse::new(
+            403,
+            "You are not allowed to delete this comment".to_string(),
+        ));
+    }
+
+    let txn = app_state
+        .db
+        .begin()
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+    // Replies made from now on wait for the comment to be deleted, earlier ones are counted
+    let comment = comment::Entity::find_by_id(comment.id)
+        .lock_exclusive()
+        .one(&txn)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?
+        .ok_or(ApiResponse::new(404, "No comment found".to_string()))?;
+    if comment.deleted_at.is_some() {
+        return Err(ApiResponse::new(
+            410,
+            "Comment has been deleted".to_string(),
+        ));
+    }
+
+    let reply_count = comment::Entity::find()
+        .filter(comment::Column::ParentId.eq(comment.id))
+        .count(&txn)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+    // Replies keep their place in the thread, so a comment with replies only loses its content
+    if reply_count > 0 {
+        let mut comment_entity = comment.into_active_model();
+        comment_entity.text = Set(String::new());
+        comment_entity.deleted_at = Set(Some(
+            Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap()),
+        ));
+        comment_entity
+            .update(&txn)
+            .await
+            .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+    } else {
+        comment
+            .delete(&txn)
+            .await
+            .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+    }
+    txn.commit()
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+    Ok(ApiResponse::json(200, "Comment deleted".to_string()))
+}
+
+/// Order of comment listings, oldest comments first
+fn oldest_first() -> SortKey {
+    SortKey {
+        name: "created_at",
+        key: Expr::col((comment::Entity, comment::Column::CreatedAt)).into(),
+        id: Expr::col((comment::Entity, comment::Column::Id)).into(),
+        descending: false,
+    }
+}
+
+fn validate_text(text: &str) -> Result<String, ApiResponse> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(ApiResponse::new(400, "Comment can't be empty".to_string()));
+    }
+    if text.chars().count() > MAX_COMMENT_LENGTH {
+        return Err(ApiResponse::new(
+            400,
+            format!("Comment can be at most {} characters", MAX_COMMENT_LENGTH),
+        ));
+    }
+    Ok(text.to_string())
+}
+
+async fn find_comment(
+    db: &DatabaseConnection,
+    comment_uuid: Uuid,
+) -> Result<comment::Model, ApiResponse> {
+    comment::Entity::find()
+        .filter(comment::Column::Uuid.eq(comment_uuid))
+        .one(db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?
+        .ok_or(ApiResponse::new(404, "No comment found".to_string()))
+}
+
+/// Comments are only reachable as long as their post is.
+async fn check_post_viewable(
+    db: &DatabaseConnection,
+    comment: &comment::Model,
+    viewer: Option<i32>,
+) -> Result<entity::post::Model, ApiResponse> {
+    let post = comment
+        .find_related(entity::post::Entity)
+        .one(db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?
+        .ok_or(ApiResponse::new(404, "No post found".to_string()))?;
+
+    find_viewable_post(db, post.uuid, viewer).await
+}
+
+/// Turn comments into their output form, loading authors, parents and reply counts in bulk.
+async fn comments_out(
+    db: &DatabaseConnection,
+    comments: Vec<comment::Model>,
+) -> Result<Vec<CommentOut>, ApiResponse> {
+    let comment_ids: Vec<i32> = comments.iter().map(|comment| comment.id).collect();
+    let user_ids: Vec<i32> = comments.iter().map(|comment| comment.user_id).collect();
+    let parent_ids: Vec<i32> = comments
+        .iter()
+        .filter_map(|comment| comment.parent_id)
+        .collect();
+
+    let authors: HashMap<i32, entity::user::Model> = entity::user::Entity::find()
+        .filter(entity::user::Column::Id.is_in(user_ids))
+        .all(db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?
+        .into_iter()
+        .map(|user| (user.id, user))
+        .collect();
+
+    let parent_uuids: HashMap<i32, Uuid> = comment::Entity::find()
+        .select_only()
+        .column(comment::Column::Id)
+        .column(comment::Column::Uuid)
+        .filter(comment::Column::Id.is_in(parent_ids))
+        .into_tuple::<(i32, Uuid)>()
+        .all(db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?
+        .into_iter()
+        .collect();
+
+    let reply_counts: HashMap<i32, i64> = comment::Entity::find()
+        .select_only()
+        .column(comment::Column::ParentId)
+        .column_as(comment::Column::Id.count(), "count")
+        .filter(comment::Column::ParentId.is_in(comment_ids))
+        .group_by(comment::Column::ParentId)
+        .into_tuple::<(i32, i64)>()
+        .all(db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?
+        .into_iter()
+        .collect();
+
+    Ok(comments
+        .into_iter()
+        .map(|comment| {
+            let deleted = comment.deleted_at.is_some();
+            let author = authors
+                .get(&comment.user_id)
+                .filter(|_| !deleted)
+                .cloned()
+                .map(PublicUserOut::from);
+            CommentOut {
+                uuid: comment.uuid,
+                parent_uuid: comment
+                    .parent_id
+                    .and_then(|parent_id| parent_uuids.get(&parent_id).copied()),
+                text: (!deleted).then_some(comment.text),
+                author,
+                reply_count: reply_counts.get(&comment.id).copied().unwrap_or(0) as u64,
+                created_at: comment.created_at,
+                updated_at: comment.updated_at,
+                deleted,
+            }
+        })
+        .collect())
+}
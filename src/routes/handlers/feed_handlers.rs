@@ -0,0 +1,240 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use actix_web::{
+    http::header::{self, HttpDate},
+    route, web, HttpMessage, HttpRequest, HttpResponse,
+};
+use chrono::{DateTime, FixedOffset};
+use entity::{post, user};
+use sea_orm::{
+    sea_query::Expr, ColumnTrait, Condition, EntityTrait, QueryFilter, QueryOrder, QuerySelect,
+};
+use sha2::{Digest, Sha256};
+
+use super::{attachment_handlers::post_attachments, media_handlers::none_match};
+use crate::utils::{
+    api_response::ApiResponse,
+    app_state,
+    constants::get_public_base_url,
+    feeds::{Enclosure, Feed, FeedEntry},
+    markdown::render_markdown,
+    post_visibility::publicly_listed,
+    uploads::media_type,
+};
+
+/// Number of the newest posts a feed holds
+const FEED_SIZE: u64 = 20;
+
+const ATOM_CONTENT_TYPE: &str = "application/atom+xml; charset=utf-8";
+const RSS_CONTENT_TYPE: &str = "application/rss+xml; charset=utf-8";
+
+#[route("posts.atom", method = "GET", method = "HEAD")]
+pub(crate) async fn posts_atom(
+    app_state: web::Data<app_state::AppState>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiResponse> {
+    let feed = post_feed(&app_state, None, "posts.atom").await?;
+    Ok(feed_response(
+        &req,
+        feed.to_atom(),
+        ATOM_CONTENT_TYPE,
+        feed.updated,
+    ))
+}
+
+#[route("posts.rss", method = "GET", method = "HEAD")]
+pub(crate) async fn posts_rss(
+    app_state: web::Data<app_state::AppState>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiResponse> {
+    let feed = post_feed(&app_state, None, "posts.rss").await?;
+    Ok(feed_response(
+        &req,
+        feed.to_rss(),
+        RSS_CONTENT_TYPE,
+        feed.updated,
+    ))
+}
+
+#[route("users/{user_id}/posts.atom", method = "GET", method = "HEAD")]
+pub(crate) async fn author_posts_atom(
+    app_state: web::Data<app_state::AppState>,
+    req: HttpRequest,
+    user_id: web::Path<i32>,
+) -> Result<HttpResponse, ApiResponse> {
+    let path = format!("users/{}/posts.atom", user_id);
+    let feed = post_feed(&app_state, Some(*user_id), &path).await?;
+    Ok(feed_response(
+        &req,
+        feed.to_atom(),
+        ATOM_CONTENT_TYPE,
+        feed.updated,
+    ))
+}
+
+#[route("users/{user_id}/posts.rss", method = "GET", method = "HEAD")]
+pub(crate) async fn author_posts_rss(
+    app_state: web::Data<app_state::AppState>,
+    req: HttpRequest,
+    user_id: web::Path<i32>,
+) -> Result<HttpResponse, ApiResponse> {
+    let path = format!("users/{}/posts.rss", user_id);
+    let feed = post_feed(&app_state, Some(*user_id), &path).await?;
+    Ok(feed_response(
+        &req,
+        feed.to_rss(),
+        RSS_CONTENT_TYPE,
+        feed.updated,
+    ))
+}
+
+/// Feed of the newest publicly listed posts, of a single author if `author_id` is given. `path`
+/// is where the feed is served below `/feeds`.
+async fn post_feed(
+    app_state: &app_state::AppState,
+    author_id: Option<i32>,
+    path: &str,
+) -> Result<Feed, ApiResponse> {
+    let db = &app_state.db;
+    let author = match author_id {
+        Some(author_id) => Some(
+            user::Entity::find_by_id(author_id)
+                .one(db)
+                .await
+                .map_err(|err| ApiResponse::new(500, err.to_string()))?
+                .ok_or(ApiResponse::new(404, "User not found".to_string()))?,
+        ),
+        None => None,
+    };
+    let by_author = match &author {
+        Some(author) => Condition::all().add(post::Column::UserId.eq(author.id)),
+        None => Condition::all(),
+    };
+
+    // Deleted and unpublished posts count as well, they change the feed by leaving it
+    let last_change: Option<DateTime<FixedOffset>> = post::Entity::find()
+        .select_only()
+        .column_as(
+            Expr::cust(
+                "max(greatest(\"post\".\"created_at\", \"post\".\"updated_at\", \
+                 \"post\".\"deleted_at\", case when \"post\".\"published_at\" <= now() then \
+                 \"post\".\"published_at\" end))",
+            ),
+            "last_change",
+        )
+        .filter(by_author.clone())
+        .into_tuple::<Option<DateTime<FixedOffset>>>()
+        .one(db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?
+        .flatten();
+
+    let posts = post::Entity::find()
+        .filter(publicly_listed())
+        .filter(by_author)
+        .order_by_desc(post::Column::PublishedAt)
+        .order_by_desc(post::Column::Id)
+        .limit(FEED_SIZE)
+        .all(db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+    let post_ids: Vec<i32> = posts.iter().map(|post| post.id).collect();
+    let mut attachments = post_attachments(app_state, &post_ids, None).await?;
+    let author_names: HashMap<i32, String> = match &author {
+        Some(author) => HashMap::from([(author.id, author.name.clone())]),
+        None => user::Entity::find()
+            .select_only()
+            .column(user::Column::Id)
+            .column(user::Column::Name)
+            .filter(user::Column::Id.is_in(posts.iter().map(|post| post.user_id)))
+            .into_tuple::<(i32, String)>()
+            .all(db)
+            .await
+            .map_err(|err| ApiResponse::new(500, err.to_string()))?
+            .into_iter()
+            .collect(),
+    };
+
+    let base_url = get_public_base_url();
+    let entries = posts
+        .into_iter()
+        .map(|post| {
+            let enclosure = attachments
+                .remove(&post.id)
+                .and_then(|attachments| attachments.into_iter().next())
+                .and_then(|attachment| {
+                    Some(Enclosure {
+                        media_type: media_type(&attachment.file_name)?,
+                        url: attachment.url,
+                    })
+                });
+            FeedEntry {
+                uuid: post.uuid,
+                link: format!("{}/post/s/{}", base_url, post.slug),
+                content_html: post
+                    .text_html
+                    .unwrap_or_else(|| render_markdown(&post.text)),
+                title: post.title,
+                author: author_names.get(&post.user_id).cloned(),
+                created_at: post.created_at,
+                updated_at: post.updated_at.unwrap_or(post.created_at),
+                enclosure,
+            }
+        })
+        .collect();
+
+    Ok(Feed {
+        title: match &author {
+            Some(author) => format!("Posts by {}", author.name),
+            None => "Posts".to_string(),
+        },
+        feed_url: format!("{}/feeds/{}", base_url, path),
+        site_url: base_url.clone(),
+        updated: last_change.unwrap_or_default(),
+        entries,
+    })
+}
+
+/// Response with a feed document, or 304 if the client has the same one already. `If-None-Match`
+/// takes precedence over `If-Modified-Since`, as the `ETag` also changes with edits which don't
+/// touch any post timestamp.
+fn feed_response(
+    req: &HttpRequest,
+    body: String,
+    content_type: &str,
+    last_modified: DateTime<FixedOffset>,
+) -> HttpResponse {
+    let etag = format!(
+        "\"{}\"",
+        hex::encode(&Sha256::digest(body.as_bytes())[..16])
+    );
+    // HTTP dates are precise to the second only
+    let last_modified =
+        HttpDate::from(UNIX_EPOCH + Duration::from_secs(last_modified.timestamp().max(0) as u64));
+
+    let not_modified = match req.headers().contains_key(header::IF_NONE_MATCH) {
+        true => none_match(req.headers(), &etag),
+        false => req
+            .get_header::<header::IfModifiedSince>()
+            .is_some_and(|since| SystemTime::from(last_modified) <= SystemTime::from(since.0)),
+    };
+    let mut response = match not_modified {
+        true => HttpResponse::NotModified(),
+        false => HttpResponse::Ok(),
+    };
+    response
+        .insert_header((header::ETAG, etag))
+        .insert_header(header::LastModified(last_modified))
+        // Readers may keep the feed, but have to ask whether it changed
+        .insert_header((header::CACHE_CONTROL, "public, no-cache"));
+    match not_modified {
+        true => response.finish(),
+        false => response
+            .insert_header((header::CONTENT_TYPE, content_type))
+            .body(body),
+    }
+}
@@ -0,0 +1,84 @@
+use actix_web::{delete, put, web};
+use chrono::{FixedOffset, Utc};
+use entity::follow;
+use sea_orm::{
+    sea_query::OnConflict, ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait,
+    QueryFilter, Set,
+};
+
+use crate::{
+    schemas::follow_schemas::FollowOut,
+    utils::{api_response::ApiResponse, app_state, jwt::Claims},
+};
+
+/// Follow a user, which lets one see their followers only posts
+#[put("follow/{user_id}")]
+pub(crate) async fn follow_user(
+    app_state: web::Data<app_state::AppState>,
+    claim: Claims,
+    user_id: web::Path<i32>,
+) -> Result<ApiResponse, ApiResponse> {
+    let user_id = user_id.into_inner();
+    if user_id == claim.id {
+        return Err(ApiResponse::new(
+            400,
+            "Users can't follow themselves".to_string(),
+        ));
+    }
+    entity::user::Entity::find_by_id(user_id)
+        .one(&app_state.db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?
+        .ok_or(ApiResponse::new(404, "User not found".to_string()))?;
+
+    // Following twice is a no-op
+    follow::Entity::insert(follow::ActiveModel {
+        follower_id: Set(claim.id),
+        followee_id: Set(user_id),
+        created_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
+    })
+    .on_conflict(
+        OnConflict::columns([follow::Column::FollowerId, follow::Column::FolloweeId])
+            .do_nothing()
+            .to_owned(),
+    )
+    .do_nothing()
+    .exec(&app_state.db)
+    .await
+    .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+    ApiResponse::serialize(200, &follow_out(&app_state.db, user_id, true).await?)
+}
+
+#[delete("follow/{user_id}")]
+pub(crate) async fn unfollow_user(
+    app_state: web::Data<app_state::AppState>,
+    claim: Claims,
+    user_id: web::Path<i32>,
+) -> Result<ApiResponse, ApiResponse> {
+    let user_id = user_id.into_inner();
+    follow::Entity::delete_by_id((claim.id, user_id))
+        .exec(&app_state.db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+    ApiResponse::serialize(200, &follow_out(&app_state.db, user_id, false).await?)
+}
+
+async fn follow_out(
+    db: &DatabaseConnection,
+    user_id: i32,
+    following: bool,
+) -> Result<FollowOut, ApiResponse> {
+    let follower_count = follow::Entity::find()
+        .filter(follow::Column::FolloweeId.eq(user_id))
+        .count(db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+    Ok(FollowOut {
+        user_id,
+        following,
+        follower_count,
+    })
+}
@@ -0,0 +1,284 @@
+use std::io::ErrorKind;
+
+use actix_files::NamedFile;
+use actix_web::{
+    http::header::{self, HeaderMap, HeaderValue},
+    route, web, HttpRequest, HttpResponse,
+};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    schemas::media_schemas::SignedMediaQuery,
+    utils::{
+        api_response::ApiResponse,
+        app_state,
+        jwt::Claims,
+        media_urls::verify_signed_url,
+        post_visibility::is_public_media,
+        uploads::{is_immutable_name, media_type},
+    },
+};
+
+/// Cache-Control of files whose content never changes under their name
+const CACHE_IMMUTABLE: &str = "public, max-age=31536000, immutable";
+/// Cache-Control of older uploads, which clients have to revalidate
+const CACHE_REVALIDATE: &str = "public, no-cache";
+
+/// Serve a stored upload from whichever storage backend is configured. Supports conditional
+/// requests by `ETag` and byte ranges. Files of posts not everyone may see are only
+/// served through signed links.
+#[route("/{name}", method = "GET", method = "HEAD")]
+pub(crate) async fn get_media(
+    app_state: web::Data<app_state::AppState>,
+    req: HttpRequest,
+    name: web::Path<String>,
+    query: web::Query<SignedMediaQuery>,
+    claim: Option<Claims>,
+) -> Result<HttpResponse, ApiResponse> {
+    let not_found = || ApiResponse::new(404, "Media not found".to_string());
+    let media_type = media_type(&name).ok_or_else(not_found)?;
+    let cache_control = match query.signature {
+        Some(_) => {
+            let remaining = verify_signed_url(&name, &query, claim.map(|claim| claim.id))?;
+            // Shared caches would hand the file out to anyone
+            format!("private, max-age={}", remaining)
+        },
+        None => {
+            let public = is_public_media(&app_state.db, &name)
+                .await
+                .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+            if !public {
+                return Err(ApiResponse::new(
+                    403,
+                    "Media needs a signed link".to_string(),
+                ));
+            }
+            match is_immutable_name(&name) {
+                true => CACHE_IMMUTABLE.to_string(),
+                false => CACHE_REVALIDATE.to_string(),
+            }
+        },
+    };
+
+    // Files which never change under their name are revalidated without reading them
+    let name_etag = immutable_etag(&name);
+    if let Some(etag) = &name_etag {
+        if none_match(req.headers(), etag) {
+            return Ok(not_modified(etag, &cache_control));
+        }
+    }
+
+    if let Some(path) = app_state.storage.local_path(&name) {
+        let file = match NamedFile::open_async(path).await {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Err(not_found()),
+            Err(err) => return Err(ApiResponse::new(500, err.to_string())),
+        };
+        // Streamed from disk, with range and conditional requests handled along the way. Files
+        // which may change are tagged by their size and modification time.
+        let mut response = file
+            .set_content_type(
+                media_type
+                    .parse()
+                    .map_err(|_| ApiResponse::new(500, "Invalid media type".to_string()))?,
+            )
+            .disable_content_disposition()
+            .use_etag(name_etag.is_none())
+            .into_response(&req);
+        let headers = response.headers_mut();
+        if let Some(etag) = &name_etag {
+            headers.insert(
+                header::ETAG,
+                HeaderValue::from_str(etag)
+                    .map_err(|err| ApiResponse::new(500, err.to_string()))?,
+            );
+        }
+        headers.insert(
+            header::CACHE_CONTROL,
+            HeaderValue::from_str(&cache_control)
+                .map_err(|err| ApiResponse::new(500, err.to_string()))?,
+        );
+        headers.insert(
+            header::X_CONTENT_TYPE_OPTIONS,
+            HeaderValue::from_static("nosniff"),
+        );
+        return Ok(response);
+    }
+
+    let storage = app_state.storage.clone();
+    let bytes = web::block(move || storage.get(&name))
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?
+        .ok_or_else(not_found)?;
+    let etag = name_etag.unwrap_or_else(|| content_etag(&bytes));
+    if none_match(req.headers(), &etag) {
+        return Ok(not_modified(&etag, &cache_control));
+    }
+    let length = bytes.len() as u64;
+
+    let range = match requested_range(req.headers(), &etag) {
+        Some(range) => match parse_range(range, length) {
+            Ok(range) => range,
+            Err(_) => {
+                return Ok(HttpResponse::RangeNotSatisfiable()
+                    .insert_header((header::CONTENT_RANGE, format!("bytes */{}", length)))
+                    .insert_header((header::ETAG, etag))
+                    .finish());
+            },
+        },
+        None => None,
+    };
+    let (mut response, body) = match range {
+        Some((start, end)) => {
+            let mut response = HttpResponse::PartialContent();
+            response.insert_header((
+                header::CONTENT_RANGE,
+                format!("bytes {}-{}/{}", start, end, length),
+            ));
+            (response, bytes[start as usize..=end as usize].to_vec())
+        },
+        None => (HttpResponse::Ok(), bytes),
+    };
+    Ok(response
+        .insert_header((header::CONTENT_TYPE, media_type))
+        .insert_header((header::ETAG, etag))
+        .insert_header((header::CACHE_CONTROL, cache_control))
+        .insert_header((header::ACCEPT_RANGES, "bytes"))
+        .insert_header((header::X_CONTENT_TYPE_OPTIONS, "nosniff"))
+        .body(body))
+}
+
+/// Entity tag of the stored file `name` if its content never changes. Content addressed names
+/// carry the hash of the content already, other immutable names are unique to a single upload.
+fn immutable_etag(name: &str) -> Option<String> {
+    if !is_immutable_name(name) {
+        return None;
+    }
+    let tag = match name.split_once('.') {
+        Some((hash, _)) if hash.len() == 64 => hash.to_string(),
+        _ => hex::encode(&Sha256::digest(name.as_bytes())[..16]),
+    };
+    Some(format!("\"{}\"", tag))
+}
+
+/// Entity tag of a file with the content `bytes`
+fn content_etag(bytes: &[u8]) -> String {
+    format!("\"{}\"", hex::encode(&Sha256::digest(bytes)[..16]))
+}
+
+fn not_modified(etag: &str, cache_control: &str) -> HttpResponse {
+    HttpResponse::NotModified()
+        .insert_header((header::ETAG, etag))
+        .insert_header((header::CACHE_CONTROL, cache_control))
+        .finish()
+}
+
+/// Whether `If-None-Match` lists `etag`, compared weakly as the header requires
+pub(crate) fn none_match(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+/// The `Range` header, unless `If-Range` asks for the whole file as it changed since
+fn requested_range<'a>(headers: &'a HeaderMap, etag: &str) -> Option<&'a str> {
+    let range = headers.get(header::RANGE)?.to_str().ok()?;
+    match headers.get(header::IF_RANGE) {
+        Some(if_range) if if_range.to_str().ok()?.trim() != etag => None,
+        _ => Some(range),
+    }
+}
+
+/// First and last byte of a single `bytes` range in a file of `length` bytes. `Ok(None)` means
+/// the whole file is served, which is also the answer to range sets and syntax we don't handle.
+/// `Err` means no byte of the range exists.
+fn parse_range(range: &str, length: u64) -> Result<Option<(u64, u64)>, ()> {
+    let Some(spec) = range.trim().strip_prefix("bytes=") else {
+        return Ok(None);
+    };
+    if spec.contains(',') {
+        return Ok(None);
+    }
+    let Some((start, end)) = spec.trim().split_once('-') else {
+        return Ok(None);
+    };
+    let (start, end) = match (start.trim(), end.trim()) {
+        // Suffix range, the last `end` bytes
+        ("", suffix) => match suffix.parse::<u64>() {
+            Ok(0) => return Err(()),
+            Ok(_) if length == 0 => return Err(()),
+            Ok(suffix) => (length.saturating_sub(suffix), length - 1),
+            Err(_) => return Ok(None),
+        },
+        (start, end) => {
+            let Ok(start) = start.parse::<u64>() else {
+                return Ok(None);
+            };
+            let end = match end {
+                "" => u64::MAX,
+                end => match end.parse::<u64>() {
+                    Ok(end) if end >= start => end,
+                    _ => return Ok(None),
+                },
+            };
+            if start >= length {
+                return Err(());
+            }
+            (start, end.min(length - 1))
+        },
+    };
+    Ok(Some((start, end)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_single_ranges() {
+        assert_eq!(parse_range("bytes=0-0", 100), Ok(Some((0, 0))));
+        assert_eq!(parse_range("bytes=10-20", 100), Ok(Some((10, 20))));
+        assert_eq!(parse_range(" bytes= 10 - 20 ", 100), Ok(Some((10, 20))));
+        // The end is cut to the last byte
+        assert_eq!(parse_range("bytes=50-500", 100), Ok(Some((50, 99))));
+    }
+
+    #[test]
+    fn parses_open_ended_ranges() {
+        assert_eq!(parse_range("bytes=10-", 100), Ok(Some((10, 99))));
+        assert_eq!(parse_range("bytes=99-", 100), Ok(Some((99, 99))));
+    }
+
+    #[test]
+    fn parses_suffix_ranges() {
+        assert_eq!(parse_range("bytes=-10", 100), Ok(Some((90, 99))));
+        assert_eq!(parse_range("bytes=-100", 100), Ok(Some((0, 99))));
+        assert_eq!(parse_range("bytes=-500", 100), Ok(Some((0, 99))));
+    }
+
+    #[test]
+    fn rejects_unsatisfiable_ranges() {
+        assert_eq!(parse_range("bytes=100-", 100), Err(()));
+        assert_eq!(parse_range("bytes=200-300", 100), Err(()));
+        assert_eq!(parse_range("bytes=-0", 100), Err(()));
+        assert_eq!(parse_range("bytes=-10", 0), Err(()));
+        assert_eq!(parse_range("bytes=0-", 0), Err(()));
+    }
+
+    #[test]
+    fn serves_whole_file_for_other_ranges() {
+        // Range sets
+        assert_eq!(parse_range("bytes=0-1,5-6", 100), Ok(None));
+        assert_eq!(parse_range("bytes=-5, 10-", 100), Ok(None));
+        // Other units and invalid syntax
+        assert_eq!(parse_range("items=0-1", 100), Ok(None));
+        assert_eq!(parse_range("bytes=20-10", 100), Ok(None));
+        assert_eq!(parse_range("bytes=a-b", 100), Ok(None));
+        assert_eq!(parse_range("bytes=-x", 100), Ok(None));
+        assert_eq!(parse_range("bytes=10", 100), Ok(None));
+    }
+}
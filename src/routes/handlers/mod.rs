@@ -1,4 +1,16 @@
+pub mod admin_handlers;
+pub mod analytics_handlers;
+pub mod attachment_handlers;
 pub mod auth_handlers;
+pub mod bookmark_handlers;
+pub mod comment_handlers;
+pub mod feed_handlers;
+pub mod follow_handlers;
 pub mod home_handlers;
+pub mod media_handlers;
 pub mod post_handlers;
+pub mod reaction_handlers;
+pub mod revision_handlers;
+pub mod slug_handlers;
+pub mod tag_handlers;
 pub mod user_handlers;
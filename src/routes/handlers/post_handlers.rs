@@ -1,214 +1,734 @@
-use std::path::PathBuf;
+use std::{collections::HashMap, mem, sync::Arc};
 
 use actix_multipart::form::MultipartForm;
-use actix_web::{get, post, web};
-use chrono::{FixedOffset, Utc};
-use entity::post;
+use actix_web::{delete, get, post, route, web, HttpRequest};
+use chrono::{DateTime, Duration, FixedOffset, Utc};
+use entity::{
+    comment, post, post_attachment,
+    sea_orm_active_enums::{PostStatus, PostVisibility},
+};
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, Set, TransactionTrait, TryIntoModel,
+    sea_query::{extension::postgres::PgExpr, Expr, Func, Query, SimpleExpr},
+    ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, DatabaseConnection, EntityTrait,
+    FromQueryResult, IntoActiveModel, ModelTrait, QueryFilter, QueryOrder, QuerySelect, Select,
+    Set, TransactionTrait,
 };
 use uuid::Uuid;
 
+use super::{
+    attachment_handlers::{attach_images, detach_all, post_attachments, MAX_ATTACHMENTS},
+    bookmark_handlers::bookmarked_posts,
+    reaction_handlers::reaction_summaries,
+    revision_handlers::save_revision,
+    slug_handlers::{change_slug, new_slug, save_post},
+    tag_handlers::{post_tags, sync_post_tags},
+};
 use crate::{
     schemas::{
-        post_schemas::{CreatePostModel, PostOut},
+        pagination_schemas::{Page, PageQuery},
+        post_schemas::{
+            CreatePostModel, PostListQuery, PostOut, PostSearchOut, PostSearchQuery, PostSortField,
+            SortOrder, UpdatePostModel,
+        },
         user_schemas::UserOut,
     },
-    utils::{api_response::ApiResponse, app_state, constants::get_max_file_size, jwt::Claims},
+    utils::{
+        api_response::ApiResponse,
+        app_state,
+        constants::get_post_restore_window_hours,
+        jwt::Claims,
+        markdown::render_markdown,
+        media::{commit_upload, remove_unreferenced},
+        pagination::{paginate, SortKey, DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE},
+        post_visibility::{find_viewable_post, listed_for},
+        search::{highlight, to_tsquery},
+        tags::parse_tags,
+        uploads::process_images,
+    },
 };
 
 #[post("create")]
 pub(crate) async fn create_post(
     app_state: web::Data<app_state::AppState>,
     claim: Claims,
-    post_model: MultipartForm<CreatePostModel>,
+    mut post_model: MultipartForm<CreatePostModel>,
 ) -> Result<ApiResponse, ApiResponse> {
-    let mut post_out;
+    let tags = parse_tags(post_model.tags.iter().map(|tags| tags.as_str()))
+        .map_err(|tag| ApiResponse::new(400, format!("Invalid tag: {}", tag)))?;
+
+    let (status, published_at) = resolve_status(
+        post_model.status.as_ref().map(|status| **status),
+        post_model
+            .publish_at
+            .as_ref()
+            .map(|publish_at| **publish_at),
+        None,
+    )?;
+
+    let slug = new_slug(&app_state.db, &post_model.title).await?;
 
-    let id = claim.id;
-    if post_model.file.is_none() {
-        post_out = create_post_without_image(app_state.clone(), claim, post_model).await?;
-    } else {
-        post_out = create_post_with_image(app_state.clone(), claim, post_model).await?;
+    if post_model.file.len() > MAX_ATTACHMENTS {
+        return Err(ApiResponse::new(
+            400,
+            format!("A post can have at most {} images", MAX_ATTACHMENTS),
+        ));
     }
+    // Decoding and encoding images takes a while
+    let files = mem::take(&mut post_model.file);
+    let images = Arc::new(
+        web::block(move || process_images(&files))
+            .await
+            .map_err(|err| ApiResponse::new(500, err.to_string()))??,
+    );
+    let alt_texts: Vec<&str> = post_model.alt_text.iter().map(|alt| alt.as_str()).collect();
 
-    let user = entity::user::Entity::find_by_id(id)
+    let txn = app_state
+        .db
+        .begin()
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+    let inserted = async {
+        let new_post = post::ActiveModel {
+            title: Set(post_model.title.clone()),
+            text: Set(post_model.text.clone()),
+            text_html: Set(Some(render_markdown(&post_model.text))),
+            uuid: Set(Uuid::new_v4()),
+            slug: Set(slug),
+            user_id: Set(claim.id),
+            status: Set(status),
+            visibility: Set(post_model
+                .visibility
+                .as_ref()
+                .map_or(PostVisibility::Public, |visibility| **visibility)),
+            created_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
+            published_at: Set(published_at),
+            ..Default::default()
+        };
+        let new_post = save_post(&txn, new_post, &post_model.title).await?;
+        attach_images(&txn, &app_state.storage, new_post.id, &images, &alt_texts).await?;
+        sync_post_tags(&txn, new_post.id, Some(tags), &new_post.text).await?;
+        Ok::<_, ApiResponse>(new_post)
+    }
+    .await;
+    let new_post = commit_upload(&app_state.db, &app_state.storage, txn, &images, inserted).await?;
+
+    let user = entity::user::Entity::find_by_id(claim.id)
         .one(&app_state.db)
         .await
         .map_err(|err| ApiResponse::new(500, err.to_string()))?;
 
+    let mut post_out = PostOut::from(new_post);
     post_out.user = user.map(|model| UserOut {
         id: model.id,
         name: model.name,
         email: model.email,
     });
 
+    enrich_posts(
+        &app_state,
+        std::slice::from_mut(&mut post_out),
+        Some(claim.id),
+    )
+    .await?;
+
     ApiResponse::serialize(201, &post_out)
 }
 
-async fn create_post_with_image(
-    app_state: web::Data<app_state::AppState>,
-    claim: Claims,
-    post_model: MultipartForm<CreatePostModel>,
-) -> Result<PostOut, ApiResponse> {
-    let in_file = post_model.file.as_ref().unwrap();
-    let check_name = in_file.file_name.clone().unwrap_or("null".to_owned());
+/// Status of a post together with the time it is or was published at
+type Publication = (PostStatus, Option<DateTime<FixedOffset>>);
 
-    let max_file_size = get_max_file_size() as usize;
+/// Work out the publication of a post from the requested `status` and `publish_at`. `current`
+/// is the publication of the post being edited, `None` for new posts.
+fn resolve_status(
+    status: Option<PostStatus>,
+    publish_at: Option<DateTime<FixedOffset>>,
+    current: Option<Publication>,
+) -> Result<Publication, ApiResponse> {
+    let now = Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap());
+    // Giving only a publication time schedules the post
+    let status = status
+        .or(publish_at.map(|_| PostStatus::Scheduled))
+        .or(current.map(|(status, _)| status))
+        .unwrap_or(PostStatus::Published);
 
-    match &check_name[check_name.len() - 4..] {
-        ".png" | ".jpg" => (),
-        _ => {
-            return Err(ApiResponse::new(
+    match status {
+        PostStatus::Draft => Ok((PostStatus::Draft, None)),
+        PostStatus::Scheduled => {
+            let publish_at = publish_at
+                .or(current.and_then(|(status, published_at)| {
+                    published_at.filter(|_| status == PostStatus::Scheduled)
+                }))
+                .ok_or(ApiResponse::new(
+                    400,
+                    "publish_at is required for scheduled posts".to_string(),
+                ))?;
+            if publish_at <= now {
+                return Err(ApiResponse::new(
+                    400,
+                    "publish_at has to be in the future".to_string(),
+                ));
+            }
+            Ok((PostStatus::Scheduled, Some(publish_at)))
+        },
+        PostStatus::Published | PostStatus::Archived if publish_at.is_some() => {
+            Err(ApiResponse::new(
                 400,
-                "Bad Request, Invalid File Name".to_string(),
-            ));
+                "publish_at can only be set for scheduled posts".to_string(),
+            ))
+        },
+        PostStatus::Published => {
+            // Posts keep their first publication time
+            let published_at = current
+                .filter(|(status, _)| {
+                    matches!(status, PostStatus::Published | PostStatus::Archived)
+                })
+                .and_then(|(_, published_at)| published_at)
+                .unwrap_or(now);
+            Ok((PostStatus::Published, Some(published_at)))
+        },
+        PostStatus::Archived => match current {
+            Some((_, published_at)) => Ok((PostStatus::Archived, published_at)),
+            None => Err(ApiResponse::new(
+                400,
+                "New posts can't be archived".to_string(),
+            )),
         },
     }
+}
 
-    match in_file.size {
-        0 => return Err(ApiResponse::new(400, "Invalid File Type".to_string())),
-        length if length > max_file_size => {
-            return Err(ApiResponse::new(400, "File too big".to_string()));
-        },
-        _ => (),
+#[get("my-posts")]
+pub(crate) async fn get_my_posts(
+    app_state: web::Data<app_state::AppState>,
+    claim: Claims,
+    list_query: web::Query<PostListQuery>,
+) -> Result<ApiResponse, ApiResponse> {
+    let select = post::Entity::find()
+        .filter(post::Column::UserId.eq(claim.id))
+        .filter(post::Column::DeletedAt.is_null());
+
+    let posts = list_posts(&app_state, select, &list_query, Some(claim.id)).await?;
+
+    ApiResponse::serialize(200, &posts)
+}
+
+#[get("all-posts")]
+pub(crate) async fn get_all_posts(
+    app_state: web::Data<app_state::AppState>,
+    claim: Option<Claims>,
+    list_query: web::Query<PostListQuery>,
+) -> Result<ApiResponse, ApiResponse> {
+    let viewer = claim.map(|claim| claim.id);
+    let select = post::Entity::find().filter(listed_for(viewer));
+
+    let posts = list_posts(&app_state, select, &list_query, viewer).await?;
+
+    ApiResponse::serialize(200, &posts)
+}
+
+/// Apply the filters and sort order of `list_query` to `select` and fetch the requested page.
+pub(crate) async fn list_posts(
+    app_state: &app_state::AppState,
+    select: Select<post::Entity>,
+    list_query: &PostListQuery,
+    viewer: Option<i32>,
+) -> Result<Page<PostOut>, ApiResponse> {
+    let db = &app_state.db;
+    if list_query.limit.is_some_and(|limit| limit > MAX_PAGE_SIZE) {
+        return Err(ApiResponse::new(
+            400,
+            format!("limit can be at most {}", MAX_PAGE_SIZE),
+        ));
     }
 
-    let txn = app_state
-        .db
-        .begin()
+    let mut condition = Condition::all();
+    if let Some(author_id) = list_query.author_id {
+        condition = condition.add(post::Column::UserId.eq(author_id));
+    }
+    if let Some(created_after) = list_query.created_after {
+        condition = condition.add(post::Column::CreatedAt.gte(created_after));
+    }
+    if let Some(created_before) = list_query.created_before {
+        condition = condition.add(post::Column::CreatedAt.lt(created_before));
+    }
+    if let Some(status) = list_query.status {
+        condition = condition.add(post::Column::Status.eq(status));
+    }
+    if let Some(visibility) = list_query.visibility {
+        condition = condition.add(post::Column::Visibility.eq(visibility));
+    }
+    let has_attachment = Expr::exists(
+        Query::select()
+            .expr(Expr::val(1))
+            .from(post_attachment::Entity)
+            .and_where(
+                Expr::col((post_attachment::Entity, post_attachment::Column::PostId))
+                    .equals((post::Entity, post::Column::Id)),
+            )
+            .to_owned(),
+    );
+    match list_query.has_image {
+        Some(true) => condition = condition.add(has_attachment),
+        Some(false) => condition = condition.add(has_attachment.not()),
+        None => (),
+    }
+    if let Some(title) = &list_query.title_contains {
+        // `%`, `_` and backslash, Postgres' default escape character, are matched literally
+        let escaped = title
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        condition = condition
+            .add(Expr::col((post::Entity, post::Column::Title)).ilike(format!("%{}%", escaped)));
+    }
+
+    let created_at = Expr::col((post::Entity, post::Column::CreatedAt));
+    let (name, key): (_, SimpleExpr) = match list_query.sort {
+        PostSortField::CreatedAt => ("created_at", created_at.into()),
+        PostSortField::UpdatedAt => (
+            "updated_at",
+            Func::coalesce([
+                Expr::col((post::Entity, post::Column::UpdatedAt)).into(),
+                created_at.into(),
+            ])
+            .into(),
+        ),
+    };
+    let sort = SortKey {
+        name,
+        key,
+        id: Expr::col((post::Entity, post::Column::Id)).into(),
+        descending: matches!(list_query.order, SortOrder::Desc),
+    };
+
+    let page = PageQuery {
+        limit: list_query.limit,
+        cursor: list_query.cursor.clone(),
+    };
+    let sort_field = list_query.sort;
+    let posts = paginate(db, select.filter(condition), &sort, &page, |post| {
+        let key = match sort_field {
+            PostSortField::CreatedAt => post.created_at,
+            PostSortField::UpdatedAt => post.updated_at.unwrap_or(post.created_at),
+        };
+        (key, post.id)
+    })
+    .await?;
+
+    let mut posts = posts.map(PostOut::from);
+    enrich_posts(app_state, &mut posts.data, viewer).await?;
+
+    Ok(posts)
+}
+
+/// Fill in the details of `posts` which are stored outside of the post table. `viewer` is the id
+/// of the authenticated user looking at the posts, if any.
+pub(crate) async fn enrich_posts(
+    app_state: &app_state::AppState,
+    posts: &mut [PostOut],
+    viewer: Option<i32>,
+) -> Result<(), ApiResponse> {
+    let db = &app_state.db;
+    if posts.is_empty() {
+        return Ok(());
+    }
+    let post_ids: Vec<i32> = posts.iter().map(|post| post.id).collect();
+
+    let comment_counts: HashMap<i32, i64> = comment::Entity::find()
+        .select_only()
+        .column(comment::Column::PostId)
+        .column_as(comment::Column::Id.count(), "count")
+        .filter(comment::Column::PostId.is_in(post_ids.clone()))
+        .filter(comment::Column::DeletedAt.is_null())
+        .group_by(comment::Column::PostId)
+        .into_tuple::<(i32, i64)>()
+        .all(db)
         .await
-        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?
+        .into_iter()
+        .collect();
 
-    let mut post_entity: post::ActiveModel = post::ActiveModel {
-        title: Set(post_model.title.clone()),
-        text: Set(post_model.text.clone()),
-        uuid: Set(Uuid::new_v4()),
-        user_id: Set(claim.id),
-        created_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
-        ..Default::default()
+    let mut reactions = reaction_summaries(db, &post_ids, viewer).await?;
+    let mut tags = post_tags(db, &post_ids).await?;
+    let mut attachments = post_attachments(app_state, &post_ids, viewer).await?;
+    let bookmarked = match viewer {
+        Some(viewer) => Some(bookmarked_posts(db, &post_ids, viewer).await?),
+        None => None,
     };
 
-    let tmp_file_path = in_file.file.path();
-    let file_name = check_name.as_str();
-
-    let time_stamp: i64 = Utc::now().timestamp();
-    let mut file_path = PathBuf::from("./public");
-    let new_file_name = format!("{}-{}", time_stamp, file_name);
-    file_path.push(new_file_name.clone());
-
-    let updated_post;
-    match std::fs::copy(tmp_file_path, file_path) {
-        Ok(_) => {
-            std::fs::remove_file(tmp_file_path).unwrap_or_default();
-            post_entity.image = Set(Some(new_file_name.clone()));
-            updated_post = post_entity
-                .save(&txn)
-                .await
-                .map_err(|err| ApiResponse::new(500, err.to_string()))?;
-
-            txn.commit()
-                .await
-                .map_err(|err| ApiResponse::new(500, err.to_string()))?;
-        },
-        Err(e) => {
-            std::fs::remove_file(tmp_file_path).unwrap_or_default();
-            txn.rollback()
-                .await
-                .map_err(|err| ApiResponse::new(500, format!("Errors: {} and {}", e, err)))?;
-            return Err(ApiResponse::new(
-                500,
-                format!("Internal server error. Details: {}", e),
-            ));
-        },
+    for post in posts.iter_mut() {
+        post.attachments = attachments.remove(&post.id).unwrap_or_default();
+        if let Some(first) = post.attachments.first() {
+            post.image = Some(first.file_name.clone());
+            post.image_url = Some(first.url.clone());
+        }
+        post.comment_count = comment_counts.get(&post.id).copied().unwrap_or(0) as u64;
+        post.reactions = reactions.remove(&post.id).unwrap_or_default();
+        post.tags = tags.remove(&post.id).unwrap_or_default();
+        post.bookmarked = bookmarked
+            .as_ref()
+            .map(|bookmarked| bookmarked.contains(&post.id));
     }
 
-    let new_post = updated_post.try_into_model().map_err(|err| {
-        ApiResponse::new(
-            201,
+    Ok(())
+}
+
+#[derive(Debug, FromQueryResult)]
+struct PostSearchRow {
+    #[sea_orm(nested)]
+    post: post::Model,
+    rank: f32,
+    title_highlight: String,
+    snippet: String,
+}
+
+#[get("search")]
+pub(crate) async fn search_posts(
+    app_state: web::Data<app_state::AppState>,
+    claim: Option<Claims>,
+    search_query: web::Query<PostSearchQuery>,
+) -> Result<ApiResponse, ApiResponse> {
+    let limit = search_query.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if limit > MAX_PAGE_SIZE {
+        return Err(ApiResponse::new(
+            400,
+            format!("limit can be at most {}", MAX_PAGE_SIZE),
+        ));
+    }
+    let tsquery_text = to_tsquery(&search_query.q).ok_or(ApiResponse::new(
+        400,
+        "Search query has no searchable words".to_string(),
+    ))?;
+
+    let tsquery = Expr::cust_with_values("to_tsquery('english', $1)", [tsquery_text]);
+    let headline = |column: post::Column, options: &str| {
+        Expr::cust_with_exprs(
             format!(
-                "Post created, but unable to return details. Error details: {}",
-                err
+                "ts_headline('english', $1, $2, 'StartSel=' || chr(2) || ', StopSel=' || chr(3) \
+                 || '{}')",
+                options
+            ),
+            [Expr::col((post::Entity, column)).into(), tsquery.clone()],
+        )
+    };
+
+    let rows = post::Entity::find()
+        .filter(listed_for(claim.as_ref().map(|claim| claim.id)))
+        .filter(Expr::cust_with_expr(
+            "\"post\".\"search_vector\" @@ $1",
+            tsquery.clone(),
+        ))
+        .column_as(
+            Expr::cust_with_expr(
+                "ts_rank_cd(\"post\".\"search_vector\", $1)",
+                tsquery.clone(),
+            ),
+            "rank",
+        )
+        .column_as(
+            headline(post::Column::Title, ", HighlightAll=true"),
+            "title_highlight",
+        )
+        .column_as(
+            headline(
+                post::Column::Text,
+                ", MaxFragments=3, MaxWords=30, MinWords=10, FragmentDelimiter=\" … \"",
             ),
+            "snippet",
         )
-    })?;
+        .order_by_desc(Expr::cust("rank"))
+        .order_by_desc(post::Column::Id)
+        .limit(limit)
+        .offset(search_query.offset.unwrap_or(0))
+        .into_model::<PostSearchRow>()
+        .all(&app_state.db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
 
-    Ok(PostOut::from(new_post))
+    let mut posts: Vec<PostOut> = rows
+        .iter()
+        .map(|row| PostOut::from(row.post.clone()))
+        .collect();
+    enrich_posts(&app_state, &mut posts, claim.map(|claim| claim.id)).await?;
+
+    let posts: Vec<PostSearchOut> = posts
+        .into_iter()
+        .zip(rows)
+        .map(|(post, row)| PostSearchOut {
+            post,
+            rank: row.rank,
+            title_highlight: highlight(&row.title_highlight),
+            snippet: highlight(&row.snippet),
+        })
+        .collect();
+
+    ApiResponse::serialize(200, &posts)
 }
 
-async fn create_post_without_image(
+#[get("{post_uuid}")]
+pub(crate) async fn get_one_post(
     app_state: web::Data<app_state::AppState>,
-    claim: Claims,
-    post_model: MultipartForm<CreatePostModel>,
+    req: HttpRequest,
+    claim: Option<Claims>,
+    post_uuid: web::Path<Uuid>,
+) -> Result<ApiResponse, ApiResponse> {
+    let viewer = claim.map(|claim| claim.id);
+    let post = find_viewable_post(&app_state.db, *post_uuid, viewer).await?;
+    app_state.views.record(&req, &post, viewer);
+    let post_out = viewable_post_out(&app_state, post, viewer).await?;
+
+    ApiResponse::serialize(200, &post_out)
+}
+
+/// Output form of a single post with its author, as shown to `viewer`
+pub(crate) async fn viewable_post_out(
+    app_state: &app_state::AppState,
+    post: post::Model,
+    viewer: Option<i32>,
 ) -> Result<PostOut, ApiResponse> {
-    let new_post = post::ActiveModel {
-        title: Set(post_model.title.clone()),
-        text: Set(post_model.text.clone()),
-        uuid: Set(Uuid::new_v4()),
-        user_id: Set(claim.id),
-        created_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
-        ..Default::default()
-    }
-    .insert(&app_state.db)
-    .await
-    .map_err(|err| ApiResponse::new(500, err.to_string()))?;
-
-    Ok(PostOut::from(new_post))
+    let user = post
+        .find_related(entity::user::Entity)
+        .one(&app_state.db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+    let mut post_out = PostOut::from(post);
+    post_out.user = user.map(|model| UserOut {
+        id: model.id,
+        name: model.name,
+        email: model.email,
+    });
+    enrich_posts(app_state, std::slice::from_mut(&mut post_out), viewer).await?;
+
+    Ok(post_out)
 }
 
-#[get("my-posts")]
-pub(crate) async fn get_my_posts(
+#[route("{post_uuid}", method = "PUT", method = "PATCH")]
+pub(crate) async fn update_post(
     app_state: web::Data<app_state::AppState>,
     claim: Claims,
+    post_uuid: web::Path<Uuid>,
+    mut post_model: MultipartForm<UpdatePostModel>,
 ) -> Result<ApiResponse, ApiResponse> {
-    let posts: Vec<PostOut> = post::Entity::find()
-        .filter(post::Column::UserId.eq(claim.id))
-        .all(&app_state.db)
+    let post = find_own_post(&app_state.db, *post_uuid, claim.id).await?;
+    if post.deleted_at.is_some() {
+        return Err(ApiResponse::new(410, "Post has been deleted".to_string()));
+    }
+
+    let drop_images = post_model.remove_image.as_ref().is_some_and(|flag| **flag);
+
+    let tags = match post_model.tags.is_empty() {
+        true => None,
+        false => Some(
+            parse_tags(post_model.tags.iter().map(|tags| tags.as_str()))
+                .map_err(|tag| ApiResponse::new(400, format!("Invalid tag: {}", tag)))?,
+        ),
+    };
+
+    // Decoding and encoding images takes a while
+    let files = mem::take(&mut post_model.file);
+    let images = Arc::new(
+        web::block(move || process_images(&files))
+            .await
+            .map_err(|err| ApiResponse::new(500, err.to_string()))??,
+    );
+    let alt_texts: Vec<&str> = post_model.alt_text.iter().map(|alt| alt.as_str()).collect();
+    let txn = app_state
+        .db
+        .begin()
         .await
-        .map_err(|err| ApiResponse::new(500, err.to_string()))?
-        .into_iter()
-        .map(PostOut::from)
-        .collect();
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+    // Concurrent edits of the post wait for each other, each one based on the one before
+    let post = lock_post(&txn, post.id).await?;
+    if post.deleted_at.is_some() {
+        return Err(ApiResponse::new(410, "Post has been deleted".to_string()));
+    }
 
-    ApiResponse::serialize(200, &posts)
+    let publication = match (&post_model.status, &post_model.publish_at) {
+        (None, None) => None,
+        (status, publish_at) => Some(resolve_status(
+            status.as_ref().map(|status| **status),
+            publish_at.as_ref().map(|publish_at| **publish_at),
+            Some((post.status, post.published_at)),
+        )?),
+    };
+
+    // Only changes of the title or text make a new revision
+    let revised = post_model
+        .title
+        .as_ref()
+        .is_some_and(|title| title.0 != post.title)
+        || post_model
+            .text
+            .as_ref()
+            .is_some_and(|text| text.0 != post.text);
+    if revised {
+        save_revision(&txn, &post, claim.id).await?;
+    }
+    let slug = match &post_model.title {
+        Some(title) => change_slug(&txn, &post, title).await?,
+        None => None,
+    };
+
+    let updated = async {
+        let old_files = match drop_images {
+            true => detach_all(&txn, post.id).await?,
+            false => Vec::new(),
+        };
+        attach_images(&txn, &app_state.storage, post.id, &images, &alt_texts).await?;
+
+        let title = post_model
+            .title
+            .as_ref()
+            .map_or(post.title.clone(), |title| title.0.clone());
+        let mut post_entity = post.into_active_model();
+
+        if let Some(title) = &post_model.title {
+            post_entity.title = Set(title.0.clone());
+        }
+        if let Some(slug) = slug {
+            post_entity.slug = Set(slug);
+        }
+        if let Some(text) = &post_model.text {
+            post_entity.text = Set(text.0.clone());
+            post_entity.text_html = Set(Some(render_markdown(text)));
+        }
+        if let Some((status, published_at)) = publication {
+            post_entity.status = Set(status);
+            post_entity.published_at = Set(published_at);
+        }
+        if let Some(visibility) = &post_model.visibility {
+            post_entity.visibility = Set(**visibility);
+        }
+        post_entity.updated_at = Set(Some(
+            Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap()),
+        ));
+
+        let updated_post = save_post(&txn, post_entity, &title).await?;
+        if tags.is_some() || post_model.text.is_some() {
+            sync_post_tags(&txn, updated_post.id, tags, &updated_post.text).await?;
+        }
+        // Dropped images uploaded again are in use once more and stay
+        remove_unreferenced(
+            &txn,
+            &app_state.storage,
+            old_files.iter().map(String::as_str),
+        )
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+        Ok::<_, ApiResponse>(updated_post)
+    }
+    .await;
+    let updated_post =
+        commit_upload(&app_state.db, &app_state.storage, txn, &images, updated).await?;
+
+    let user = entity::user::Entity::find_by_id(claim.id)
+        .one(&app_state.db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+    let mut post_out = PostOut::from(updated_post);
+    post_out.user = user.map(UserOut::from);
+    enrich_posts(
+        &app_state,
+        std::slice::from_mut(&mut post_out),
+        Some(claim.id),
+    )
+    .await?;
+
+    ApiResponse::serialize(200, &post_out)
 }
 
-#[get("all-posts")]
-pub(crate) async fn get_all_posts(
+#[delete("{post_uuid}")]
+pub(crate) async fn delete_post(
     app_state: web::Data<app_state::AppState>,
+    claim: Claims,
+    post_uuid: web::Path<Uuid>,
 ) -> Result<ApiResponse, ApiResponse> {
-    let posts: Vec<PostOut> = post::Entity::find()
-        .all(&app_state.db)
+    let post = find_own_post(&app_state.db, *post_uuid, claim.id).await?;
+    if post.deleted_at.is_some() {
+        return Err(ApiResponse::new(410, "Post has been deleted".to_string()));
+    }
+
+    let mut post_entity = post.into_active_model();
+    post_entity.deleted_at = Set(Some(
+        Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap()),
+    ));
+    post_entity
+        .update(&app_state.db)
         .await
-        .map_err(|err| ApiResponse::new(500, err.to_string()))?
-        .into_iter()
-        .map(PostOut::from)
-        .collect();
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
 
-    ApiResponse::serialize(200, &posts)
+    Ok(ApiResponse::json(
+        200,
+        format!(
+            "Post deleted. It can be restored within {} hours.",
+            get_post_restore_window_hours()
+        ),
+    ))
 }
 
-#[get("{post_uuid}")]
-pub(crate) async fn get_one_post(
+#[post("{post_uuid}/restore")]
+pub(crate) async fn restore_post(
     app_state: web::Data<app_state::AppState>,
+    claim: Claims,
     post_uuid: web::Path<Uuid>,
 ) -> Result<ApiResponse, ApiResponse> {
-    let post: PostOut = post::Entity::find()
-        .filter(post::Column::Uuid.eq(*post_uuid))
-        .find_also_related(entity::user::Entity)
-        .one(&app_state.db)
+    let post = find_own_post(&app_state.db, *post_uuid, claim.id).await?;
+    let deleted_at = post
+        .deleted_at
+        .ok_or(ApiResponse::new(400, "Post is not deleted".to_string()))?;
+
+    if deleted_at + Duration::hours(get_post_restore_window_hours()) < Utc::now() {
+        return Err(ApiResponse::new(
+            410,
+            "Restore window for this post has expired".to_string(),
+        ));
+    }
+
+    let mut post_entity = post.into_active_model();
+    post_entity.deleted_at = Set(None);
+    let restored_post = post_entity
+        .update(&app_state.db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+    let post_out = viewable_post_out(&app_state, restored_post, Some(claim.id)).await?;
+
+    ApiResponse::serialize(200, &post_out)
+}
+
+/// Find a post by its uuid, making sure it belongs to the user with `user_id`.
+pub(crate) async fn find_own_post(
+    db: &DatabaseConnection,
+    post_uuid: Uuid,
+    user_id: i32,
+) -> Result<post::Model, ApiResponse> {
+    let post = post::Entity::find()
+        .filter(post::Column::Uuid.eq(post_uuid))
+        .one(db)
         .await
         .map_err(|err| ApiResponse::new(500, err.to_string()))?
-        .map(|post| {
-            let mut post_out = PostOut::from(post.0);
-            post_out.user = post.1.map(|model| UserOut {
-                id: model.id,
-                name: model.name,
-                email: model.email,
-            });
-            post_out
-        })
         .ok_or(ApiResponse::new(404, "No post found".to_string()))?;
 
-    ApiResponse::serialize(200, &post)
+    if post.user_id != user_id {
+        return Err(ApiResponse::new(
+            403,
+            "You are not allowed to modify this post".to_string(),
+        ));
+    }
+
+    Ok(post)
+}
+
+/// Lock the row of a post until the transaction `db` ends, so concurrent changes to the post
+/// and the rows belonging to it run one after another. Returns the post as it is now.
+pub(crate) async fn lock_post<C: ConnectionTrait>(
+    db: &C,
+    post_id: i32,
+) -> Result<post::Model, ApiResponse> {
+    post::Entity::find_by_id(post_id)
+        .lock_exclusive()
+        .one(db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?
+        .ok_or(ApiResponse::new(404, "No post found".to_string()))
 }
@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+
+use actix_web::{delete, get, put, web};
+use chrono::{FixedOffset, Utc};
+use entity::reaction;
+use sea_orm::{
+    sea_query::{Expr, OnConflict},
+    ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QuerySelect, Set,
+};
+use uuid::Uuid;
+
+use crate::{
+    schemas::{
+        pagination_schemas::{Page, PageQuery},
+        reaction_schemas::{ReactionListQuery, ReactionOut, ReactionSummary},
+        user_schemas::PublicUserOut,
+    },
+    utils::{
+        api_response::ApiResponse,
+        app_state,
+        constants::get_reaction_kinds,
+        jwt::Claims,
+        pagination::{paginate, SortKey},
+        post_visibility::find_viewable_post,
+    },
+};
+
+#[put("{post_uuid}/reactions/{kind}")]
+pub(crate) async fn react(
+    app_state: web::Data<app_state::AppState>,
+    claim: Claims,
+    path: web::Path<(Uuid, String)>,
+) -> Result<ApiResponse, ApiResponse> {
+    let (post_uuid, kind) = path.into_inner();
+    let kind = validate_kind(&kind)?;
+    let post = find_viewable_post(&app_state.db, post_uuid, Some(claim.id)).await?;
+
+    // The unique index on (post, kind, user) makes reacting twice a no-op, even when racing
+    reaction::Entity::insert(reaction::ActiveModel {
+        user_id: Set(claim.id),
+        post_id: Set(post.id),
+        kind: Set(kind),
+        created_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
+        ..Default::default()
+    })
+    .on_conflict(
+        OnConflict::columns([
+            reaction::Column::PostId,
+            reaction::Column::Kind,
+            reaction::Column::UserId,
+        ])
+        .do_nothing()
+        .to_owned(),
+    )
+    .do_nothing()
+    .exec(&app_state.db)
+    .await
+    .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+    let summary = reaction_summaries(&app_state.db, &[post.id], Some(claim.id))
+        .await?
+        .remove(&post.id)
+        .unwrap_or_default();
+
+    ApiResponse::serialize(200, &summary)
+}
+
+#[delete("{post_uuid}/reactions/{kind}")]
+pub(crate) async fn unreact(
+    app_state: web::Data<app_state::AppState>,
+    claim: Claims,
+    path: web::Path<(Uuid, String)>,
+) -> Result<ApiResponse, ApiResponse> {
+    let (post_uuid, kind) = path.into_inner();
+    let kind = validate_kind(&kind)?;
+    let post = find_viewable_post(&app_state.db, post_uuid, Some(claim.id)).await?;
+
+    reaction::Entity::delete_many()
+        .filter(reaction::Column::PostId.eq(post.id))
+        .filter(reaction::Column::UserId.eq(claim.id))
+        .filter(reaction::Column::Kind.eq(kind))
+        .exec(&app_state.db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+    let summary = reaction_summaries(&app_state.db, &[post.id], Some(claim.id))
+        .await?
+        .remove(&post.id)
+        .unwrap_or_default();
+
+    ApiResponse::serialize(200, &summary)
+}
+
+#[get("{post_uuid}/reactions")]
+pub(crate) async fn get_post_reactions(
+    app_state: web::Data<app_state::AppState>,
+    claim: Option<Claims>,
+    post_uuid: web::Path<Uuid>,
+    list_query: web::Query<ReactionListQuery>,
+) -> Result<ApiResponse, ApiResponse> {
+    let viewer = claim.map(|claim| claim.id);
+    let post = find_viewable_post(&app_state.db, *post_uuid, viewer).await?;
+
+    let mut select = reaction::Entity::find().filter(reaction::Column::PostId.eq(post.id));
+    if let Some(kind) = &list_query.kind {
+        select = select.filter(reaction::Column::Kind.eq(validate_kind(kind)?));
+    }
+
+    let sort = SortKey {
+        name: "created_at",
+        key: Expr::col((reaction::Entity, reaction::Column::CreatedAt)).into(),
+        id: Expr::col((reaction::Entity, reaction::Column::Id)).into(),
+        descending: true,
+    };
+    let page = PageQuery {
+        limit: list_query.limit,
+        cursor: list_query.cursor.clone(),
+    };
+    let reactions = paginate(&app_state.db, select, &sort, &page, |reaction| {
+        (reaction.created_at, reaction.id)
+    })
+    .await?;
+
+    let user_ids: Vec<i32> = reactions
+        .data
+        .iter()
+        .map(|reaction| reaction.user_id)
+        .collect();
+    let users: HashMap<i32, entity::user::Model> = entity::user::Entity::find()
+        .filter(entity::user::Column::Id.is_in(user_ids))
+        .all(&app_state.db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?
+        .into_iter()
+        .map(|user| (user.id, user))
+        .collect();
+
+    let reactions: Page<ReactionOut> = reactions.map(|reaction| ReactionOut {
+        user: users
+            .get(&reaction.user_id)
+            .cloned()
+            .map(PublicUserOut::from),
+        kind: reaction.kind,
+        created_at: reaction.created_at,
+    });
+
+    ApiResponse::serialize(200, &reactions)
+}
+
+/// Count the reactions on each of `post_ids` per kind. With a `viewer` the kinds they used are
+/// included as well. Posts without reactions are left out of the map.
+pub(crate) async fn reaction_summaries(
+    db: &DatabaseConnection,
+    post_ids: &[i32],
+    viewer: Option<i32>,
+) -> Result<HashMap<i32, ReactionSummary>, ApiResponse> {
+    let mut summaries: HashMap<i32, ReactionSummary> = HashMap::new();
+
+    let counts = reaction::Entity::find()
+        .select_only()
+        .column(reaction::Column::PostId)
+        .column(reaction::Column::Kind)
+        .column_as(reaction::Column::Id.count(), "count")
+        .filter(reaction::Column::PostId.is_in(post_ids.to_vec()))
+        .group_by(reaction::Column::PostId)
+        .group_by(reaction::Column::Kind)
+        .into_tuple::<(i32, String, i64)>()
+        .all(db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+    for (post_id, kind, count) in counts {
+        summaries
+            .entry(post_id)
+            .or_default()
+            .reactions
+            .insert(kind, count as u64);
+    }
+
+    if let Some(viewer) = viewer {
+        for post_id in post_ids {
+            summaries.entry(*post_id).or_default().my_reactions = Some(Vec::new());
+        }
+
+        let mine = reaction::Entity::find()
+            .select_only()
+            .column(reaction::Column::PostId)
+            .column(reaction::Column::Kind)
+            .filter(reaction::Column::PostId.is_in(post_ids.to_vec()))
+            .filter(reaction::Column::UserId.eq(viewer))
+            .into_tuple::<(i32, String)>()
+            .all(db)
+            .await
+            .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+        for (post_id, kind) in mine {
+            if let Some(my_reactions) = summaries.entry(post_id).or_default().my_reactions.as_mut()
+            {
+                my_reactions.push(kind);
+            }
+        }
+    }
+
+    Ok(summaries)
+}
+
+/// Make sure `kind` is one of the configured reaction kinds.
+fn validate_kind(kind: &str) -> Result<String, ApiResponse> {
+    let kind = kind.trim().to_lowercase();
+    if !get_reaction_kinds().contains(&kind) {
+        return Err(ApiResponse::new(
+            400,
+            format!(
+                "Unknown reaction, allowed are: {}",
+                get_reaction_kinds().join(", ")
+            ),
+        ));
+    }
+    Ok(kind)
+}
@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+
+use actix_web::{get, post, web};
+use chrono::{FixedOffset, Utc};
+use entity::{post, post_revision};
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection,
+    EntityTrait, IntoActiveModel, QueryFilter, QuerySelect, Set, TransactionTrait,
+};
+use similar::{ChangeTag, TextDiff};
+use uuid::Uuid;
+
+use super::{
+    post_handlers::{enrich_posts, find_own_post, lock_post},
+    slug_handlers::{change_slug, save_post},
+    tag_handlers::sync_post_tags,
+};
+use crate::{
+    schemas::{
+        pagination_schemas::{Page, PageQuery},
+        post_schemas::PostOut,
+        revision_schemas::{DiffChange, DiffOp, RevisionDiffOut, RevisionDiffQuery, RevisionOut},
+        user_schemas::UserOut,
+    },
+    utils::{
+        api_response::ApiResponse,
+        app_state,
+        jwt::Claims,
+        markdown::render_markdown,
+        pagination::{paginate, SortKey},
+    },
+};
+
+/// List the previous versions of a post, newest first. Only the author may see them.
+#[get("{post_uuid}/revisions")]
+pub(crate) async fn get_post_revisions(
+    app_state: web::Data<app_state::AppState>,
+    claim: Claims,
+    post_uuid: web::Path<Uuid>,
+    page: web::Query<PageQuery>,
+) -> Result<ApiResponse, ApiResponse> {
+    let post = find_own_post(&app_state.db, *post_uuid, claim.id).await?;
+
+    let select = post_revision::Entity::find().filter(post_revision::Column::PostId.eq(post.id));
+    let sort = SortKey {
+        name: "created_at",
+        key: Expr::col((post_revision::Entity, post_revision::Column::CreatedAt)).into(),
+        id: Expr::col((post_revision::Entity, post_revision::Column::Id)).into(),
+        descending: true,
+    };
+    let revisions = paginate(&app_state.db, select, &sort, &page, |revision| {
+        (revision.created_at, revision.id)
+    })
+    .await?;
+
+    let prev_cursor = revisions.prev_cursor.clone();
+    let next_cursor = revisions.next_cursor.clone();
+    let data = revisions_out(&app_state.db, revisions.data).await?;
+
+    ApiResponse::serialize(
+        200,
+        &Page {
+            data,
+            next_cursor,
+            prev_cursor,
+        },
+    )
+}
+
+/// Compare two versions of a post
+#[get("{post_uuid}/revisions/diff")]
+pub(crate) async fn diff_post_revisions(
+    app_state: web::Data<app_state::AppState>,
+    claim: Claims,
+    post_uuid: web::Path<Uuid>,
+    diff_query: web::Query<RevisionDiffQuery>,
+) -> Result<ApiResponse, ApiResponse> {
+    let post = find_own_post(&app_state.db, *post_uuid, claim.id).await?;
+
+    let from = find_revision(&app_state.db, post.id, diff_query.from).await?;
+    let (to_title, to_text) = match diff_query.to {
+        Some(number) => {
+            let to = find_revision(&app_state.db, post.id, number).await?;
+            (to.title, to.text)
+        },
+        None => (post.title, post.text),
+    };
+
+    ApiResponse::serialize(
+        200,
+        &RevisionDiffOut {
+            from: diff_query.from,
+            to: diff_query.to,
+            title: changes(TextDiff::from_words(&from.title, &to_title)),
+            text: changes(TextDiff::from_lines(&from.text, &to_text)),
+        },
+    )
+}
+
+#[get("{post_uuid}/revisions/{number}")]
+pub(crate) async fn get_post_revision(
+    app_state: web::Data<app_state::AppState>,
+    claim: Claims,
+    path: web::Path<(Uuid, i32)>,
+) -> Result<ApiResponse, ApiResponse> {
+    let (post_uuid, number) = path.into_inner();
+    let post = find_own_post(&app_state.db, post_uuid, claim.id).await?;
+    let revision = find_revision(&app_state.db, post.id, number).await?;
+
+    let revision_out = revisions_out(&app_state.db, vec![revision])
+        .await?
+        .pop()
+        .ok_or(ApiResponse::new(404, "No revision found".to_string()))?;
+
+    ApiResponse::serialize(200, &revision_out)
+}
+
+/// Bring back the title and text of a previous version. The current version is kept as a new
+/// revision, so restoring can be undone as well.
+#[post("{post_uuid}/revisions/{number}/restore")]
+pub(crate) async fn restore_post_revision(
+    app_state: web::Data<app_state::AppState>,
+    claim: Claims,
+    path: web::Path<(Uuid, i32)>,
+) -> Result<ApiResponse, ApiResponse> {
+    let (post_uuid, number) = path.into_inner();
+    let post = find_own_post(&app_state.db, post_uuid, claim.id).await?;
+    if post.deleted_at.is_some() {
+        return Err(ApiResponse::new(410, "Post has been deleted".to_string()));
+    }
+    let revision = find_revision(&app_state.db, post.id, number).await?;
+
+    let txn = app_state
+        .db
+        .begin()
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+    save_revision(&txn, &post, claim.id).await?;
+    let slug = change_slug(&txn, &post, &revision.title).await?;
+
+    let mut post_entity = post.into_active_model();
+    if let Some(slug) = slug {
+        post_entity.slug = Set(slug);
+    }
+    post_entity.title = Set(revision.title.clone());
+    post_entity.text_html = Set(Some(render_markdown(&revision.text)));
+    post_entity.text = Set(revision.text);
+    post_entity.updated_at = Set(Some(
+        Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap()),
+    ));
+    let updated_post = save_post(&txn, post_entity, &revision.title).await?;
+    sync_post_tags(&txn, updated_post.id, None, &updated_post.text).await?;
+    txn.commit()
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+    let user = entity::user::Entity::find_by_id(claim.id)
+        .one(&app_state.db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+    let mut post_out = PostOut::from(updated_post);
+    post_out.user = user.map(UserOut::from);
+    enrich_posts(
+        &app_state,
+        std::slice::from_mut(&mut post_out),
+        Some(claim.id),
+    )
+    .await?;
+
+    ApiResponse::serialize(200, &post_out)
+}
+
+/// Keep the current title and text of `post` as its next revision. Meant to be called in the
+/// transaction which changes them.
+pub(crate) async fn save_revision<C: ConnectionTrait>(
+    db: &C,
+    post: &post::Model,
+    editor_id: i32,
+) -> Result<(), ApiResponse> {
+    // Concurrent edits would take the same number, they wait for each other on the post row
+    let post = lock_post(db, post.id).await?;
+    let last_number: Option<i32> = post_revision::Entity::find()
+        .select_only()
+        .column_as(post_revision::Column::Number.max(), "number")
+        .filter(post_revision::Column::PostId.eq(post.id))
+        .into_tuple()
+        .one(db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?
+        .flatten();
+
+    post_revision::ActiveModel {
+        post_id: Set(post.id),
+        number: Set(last_number.unwrap_or(0) + 1),
+        title: Set(post.title.clone()),
+        text: Set(post.text.clone()),
+        editor_id: Set(editor_id),
+        created_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+    Ok(())
+}
+
+async fn find_revision(
+    db: &DatabaseConnection,
+    post_id: i32,
+    number: i32,
+) -> Result<post_revision::Model, ApiResponse> {
+    post_revision::Entity::find()
+        .filter(post_revision::Column::PostId.eq(post_id))
+        .filter(post_revision::Column::Number.eq(number))
+        .one(db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?
+        .ok_or(ApiResponse::new(404, "No revision found".to_string()))
+}
+
+/// Turn revisions into their output form, loading the editors in bulk.
+async fn revisions_out(
+    db: &DatabaseConnection,
+    revisions: Vec<post_revision::Model>,
+) -> Result<Vec<RevisionOut>, ApiResponse> {
+    let editor_ids: Vec<i32> = revisions
+        .iter()
+        .map(|revision| revision.editor_id)
+        .collect();
+    let editors: HashMap<i32, entity::user::Model> = entity::user::Entity::find()
+        .filter(entity::user::Column::Id.is_in(editor_ids))
+        .all(db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?
+        .into_iter()
+        .map(|user| (user.id, user))
+        .collect();
+
+    Ok(revisions
+        .into_iter()
+        .map(|revision| RevisionOut {
+            editor: editors.get(&revision.editor_id).cloned().map(UserOut::from),
+            number: revision.number,
+            title: revision.title,
+            text: revision.text,
+            created_at: revision.created_at,
+        })
+        .collect())
+}
+
+/// Merge consecutive changes of the same kind
+fn changes<'a>(diff: TextDiff<'a, 'a, 'a, str>) -> Vec<DiffChange> {
+    let mut changes: Vec<DiffChange> = Vec::new();
+    for change in diff.iter_all_changes() {
+        let op = match change.tag() {
+            ChangeTag::Equal => DiffOp::Equal,
+            ChangeTag::Insert => DiffOp::Insert,
+            ChangeTag::Delete => DiffOp::Delete,
+        };
+        match changes.last_mut() {
+            Some(last) if last.op == op => last.value.push_str(change.value()),
+            _ => changes.push(DiffChange {
+                op,
+                value: change.value().to_string(),
+            }),
+        }
+    }
+    changes
+}
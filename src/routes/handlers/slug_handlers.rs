@@ -0,0 +1,196 @@
+use std::collections::HashSet;
+
+use actix_web::{get, web, HttpRequest};
+use chrono::{FixedOffset, Utc};
+use entity::{post, post_slug_history};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseTransaction, DbErr, EntityTrait,
+    ModelTrait, QueryFilter, QuerySelect, Set, SqlErr, TransactionTrait, TryIntoModel,
+};
+
+use super::post_handlers::viewable_post_out;
+use crate::utils::{
+    api_response::ApiResponse, app_state, jwt::Claims, post_visibility::check_viewable,
+    slugs::slugify,
+};
+
+/// Attempts at saving a post before giving up on finding a free slug
+const MAX_SLUG_ATTEMPTS: usize = 10;
+
+/// Open a post by its slug. Slugs the post had before its title changed redirect to the
+/// current one.
+#[get("s/{slug}")]
+pub(crate) async fn get_post_by_slug(
+    app_state: web::Data<app_state::AppState>,
+    req: HttpRequest,
+    claim: Option<Claims>,
+    slug: web::Path<String>,
+) -> Result<ApiResponse, ApiResponse> {
+    let viewer = claim.map(|claim| claim.id);
+
+    let post = post::Entity::find()
+        .filter(post::Column::Slug.eq(slug.as_str()))
+        .one(&app_state.db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+    if let Some(post) = post {
+        let post = check_viewable(&app_state.db, post, viewer).await?;
+        app_state.views.record(&req, &post, viewer);
+        let post_out = viewable_post_out(&app_state, post, viewer).await?;
+        return ApiResponse::serialize(200, &post_out);
+    }
+
+    let old_slug = post_slug_history::Entity::find_by_id(slug.into_inner())
+        .one(&app_state.db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?
+        .ok_or(ApiResponse::new(404, "No post found".to_string()))?;
+    let post = old_slug
+        .find_related(post::Entity)
+        .one(&app_state.db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?
+        .ok_or(ApiResponse::new(404, "No post found".to_string()))?;
+    let post = check_viewable(&app_state.db, post, viewer).await?;
+
+    Ok(ApiResponse::redirect(301, format!("/post/s/{}", post.slug)))
+}
+
+/// Find a free slug for a new post titled `title`, adding a numeric suffix on collisions.
+pub(crate) async fn new_slug<C: ConnectionTrait>(
+    db: &C,
+    title: &str,
+) -> Result<String, ApiResponse> {
+    let base = slugify(title);
+
+    // Slugs only consist of letters, digits and dashes, nothing to escape for LIKE
+    let pattern = format!("{}%", base);
+    let mut taken: HashSet<String> = post::Entity::find()
+        .select_only()
+        .column(post::Column::Slug)
+        .filter(post::Column::Slug.like(&pattern))
+        .into_tuple::<String>()
+        .all(db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?
+        .into_iter()
+        .collect();
+    taken.extend(
+        post_slug_history::Entity::find()
+            .select_only()
+            .column(post_slug_history::Column::Slug)
+            .filter(post_slug_history::Column::Slug.like(&pattern))
+            .into_tuple::<String>()
+            .all(db)
+            .await
+            .map_err(|err| ApiResponse::new(500, err.to_string()))?,
+    );
+
+    Ok((1..)
+        .map(|suffix| match suffix {
+            1 => base.clone(),
+            suffix => format!("{}-{}", base, suffix),
+        })
+        .find(|slug| !taken.contains(slug))
+        .unwrap_or(base))
+}
+
+/// Insert or update `post_entity` in the transaction `txn`. When a concurrent save took its slug
+/// in the meantime, the next free slug for `title` is used instead.
+pub(crate) async fn save_post(
+    txn: &DatabaseTransaction,
+    mut post_entity: post::ActiveModel,
+    title: &str,
+) -> Result<post::Model, ApiResponse> {
+    for _ in 0..MAX_SLUG_ATTEMPTS {
+        // A failed statement aborts the transaction, up to the savepoint it ran in
+        let savepoint = txn
+            .begin()
+            .await
+            .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+        match post_entity.clone().save(&savepoint).await {
+            Ok(saved) => {
+                savepoint
+                    .commit()
+                    .await
+                    .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+                return saved
+                    .try_into_model()
+                    .map_err(|err| ApiResponse::new(500, err.to_string()));
+            },
+            Err(err) if is_slug_conflict(&err) => {
+                savepoint
+                    .rollback()
+                    .await
+                    .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+                post_entity.slug = Set(new_slug(txn, title).await?);
+            },
+            Err(err) => return Err(ApiResponse::new(500, err.to_string())),
+        }
+    }
+    Err(ApiResponse::new(
+        503,
+        "The post could not be given a free slug, try again".to_string(),
+    ))
+}
+
+/// Whether `err` is caused by another post having the slug
+fn is_slug_conflict(err: &DbErr) -> bool {
+    matches!(
+        err.sql_err(),
+        Some(SqlErr::UniqueConstraintViolation(message)) if message.contains("idx-post-slug")
+    )
+}
+
+/// Slug of `post` once it is retitled to `title`. The current slug is kept in the history so it
+/// keeps redirecting. Returns `None` when the slug stays the same. Meant to be called in the
+/// transaction which changes the title.
+pub(crate) async fn change_slug<C: ConnectionTrait>(
+    db: &C,
+    post: &post::Model,
+    title: &str,
+) -> Result<Option<String>, ApiResponse> {
+    if slugify(title) == slugify(&post.title) {
+        return Ok(None);
+    }
+
+    // A post going back to an earlier title gets its earlier slug back
+    let base = slugify(title);
+    let reclaimed = post_slug_history::Entity::find()
+        .filter(post_slug_history::Column::PostId.eq(post.id))
+        .filter(post_slug_history::Column::Slug.like(format!("{}%", base)))
+        .all(db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?
+        .into_iter()
+        .find(|old_slug| {
+            old_slug.slug.strip_prefix(&base).is_some_and(|suffix| {
+                suffix.is_empty()
+                    || suffix
+                        .strip_prefix('-')
+                        .is_some_and(|number| number.parse::<u32>().is_ok())
+            })
+        });
+    let slug = match reclaimed {
+        Some(old_slug) => {
+            let slug = old_slug.slug.clone();
+            old_slug
+                .delete(db)
+                .await
+                .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+            slug
+        },
+        None => new_slug(db, title).await?,
+    };
+
+    post_slug_history::ActiveModel {
+        slug: Set(post.slug.clone()),
+        post_id: Set(post.id),
+        created_at: Set(Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())),
+    }
+    .insert(db)
+    .await
+    .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+    Ok(Some(slug))
+}
@@ -0,0 +1,236 @@
+use std::collections::{BTreeSet, HashMap};
+
+use actix_web::{get, web};
+use chrono::{FixedOffset, Utc};
+use entity::{post, post_tag, tag};
+use sea_orm::{
+    sea_query::{Expr, OnConflict, Query},
+    ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait, JoinType, QueryFilter,
+    QueryOrder, QuerySelect, RelationTrait, Set,
+};
+
+use super::post_handlers::list_posts;
+use crate::{
+    schemas::{
+        post_schemas::PostListQuery,
+        tag_schemas::{TagAutocompleteQuery, TagCountOut, TagListQuery},
+    },
+    utils::{
+        api_response::ApiResponse,
+        app_state,
+        jwt::Claims,
+        pagination::{DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE},
+        post_visibility::{listed_for, publicly_listed},
+        tags::{extract_hashtags, normalize_tag, normalize_tag_prefix},
+    },
+};
+
+const DEFAULT_AUTOCOMPLETE_SIZE: u64 = 10;
+
+#[get("tag/{name}")]
+pub(crate) async fn get_tag_posts(
+    app_state: web::Data<app_state::AppState>,
+    claim: Option<Claims>,
+    name: web::Path<String>,
+    list_query: web::Query<PostListQuery>,
+) -> Result<ApiResponse, ApiResponse> {
+    let name = normalize_tag(&name).ok_or(ApiResponse::new(400, "Invalid tag".to_string()))?;
+    let tag = tag::Entity::find()
+        .filter(tag::Column::Name.eq(name))
+        .one(&app_state.db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?
+        .ok_or(ApiResponse::new(404, "No tag found".to_string()))?;
+
+    let viewer = claim.map(|claim| claim.id);
+    let select = post::Entity::find().filter(listed_for(viewer)).filter(
+        post::Column::Id.in_subquery(
+            Query::select()
+                .column(post_tag::Column::PostId)
+                .from(post_tag::Entity)
+                .and_where(post_tag::Column::TagId.eq(tag.id))
+                .to_owned(),
+        ),
+    );
+
+    let posts = list_posts(&app_state, select, &list_query, viewer).await?;
+
+    ApiResponse::serialize(200, &posts)
+}
+
+/// Tags ordered by the number of publicly listed posts using them
+#[get("")]
+pub(crate) async fn get_tags(
+    app_state: web::Data<app_state::AppState>,
+    list_query: web::Query<TagListQuery>,
+) -> Result<ApiResponse, ApiResponse> {
+    let limit = check_limit(list_query.limit, DEFAULT_PAGE_SIZE)?;
+
+    let tags = tag_counts(&app_state.db, None, limit, list_query.offset.unwrap_or(0)).await?;
+
+    ApiResponse::serialize(200, &tags)
+}
+
+/// Tags starting with the given text, most used first
+#[get("autocomplete")]
+pub(crate) async fn autocomplete_tags(
+    app_state: web::Data<app_state::AppState>,
+    autocomplete_query: web::Query<TagAutocompleteQuery>,
+) -> Result<ApiResponse, ApiResponse> {
+    let limit = check_limit(autocomplete_query.limit, DEFAULT_AUTOCOMPLETE_SIZE)?;
+    let prefix = normalize_tag_prefix(&autocomplete_query.q)
+        .ok_or(ApiResponse::new(400, "Invalid tag".to_string()))?;
+
+    let tags = tag_counts(&app_state.db, Some(&prefix), limit, 0).await?;
+
+    ApiResponse::serialize(200, &tags)
+}
+
+/// Replace the tags of a post. `explicit` are the tags set by the author, `None` keeps the
+/// current ones. The `#hashtags` of `text` are always added. Meant to run in the transaction
+/// saving the post.
+pub(crate) async fn sync_post_tags<C: ConnectionTrait>(
+    txn: &C,
+    post_id: i32,
+    explicit: Option<BTreeSet<String>>,
+    text: &str,
+) -> Result<(), ApiResponse> {
+    let explicit = match explicit {
+        Some(explicit) => explicit,
+        None => tag::Entity::find()
+            .select_only()
+            .column(tag::Column::Name)
+            .join(JoinType::InnerJoin, tag::Relation::PostTag.def())
+            .filter(post_tag::Column::PostId.eq(post_id))
+            .filter(post_tag::Column::Explicit.eq(true))
+            .into_tuple::<String>()
+            .all(txn)
+            .await
+            .map_err(|err| ApiResponse::new(500, err.to_string()))?
+            .into_iter()
+            .collect(),
+    };
+    let names: BTreeSet<String> = explicit
+        .iter()
+        .cloned()
+        .chain(extract_hashtags(text))
+        .collect();
+
+    post_tag::Entity::delete_many()
+        .filter(post_tag::Column::PostId.eq(post_id))
+        .exec(txn)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+    if !names.is_empty() {
+        let now = Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap());
+        // Tags are shared, another post may create the same tag at the same time
+        tag::Entity::insert_many(names.iter().map(|name| tag::ActiveModel {
+            name: Set(name.clone()),
+            created_at: Set(now),
+            ..Default::default()
+        }))
+        .on_conflict(
+            OnConflict::column(tag::Column::Name)
+                .do_nothing()
+                .to_owned(),
+        )
+        .do_nothing()
+        .exec(txn)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+        let tags = tag::Entity::find()
+            .filter(tag::Column::Name.is_in(names.iter().cloned()))
+            .all(txn)
+            .await
+            .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+        post_tag::Entity::insert_many(tags.into_iter().map(|tag| post_tag::ActiveModel {
+            post_id: Set(post_id),
+            explicit: Set(explicit.contains(&tag.name)),
+            tag_id: Set(tag.id),
+        }))
+        .exec(txn)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+    }
+
+    Ok(())
+}
+
+/// Names of the tags of each of `post_ids`, sorted by name
+pub(crate) async fn post_tags(
+    db: &DatabaseConnection,
+    post_ids: &[i32],
+) -> Result<HashMap<i32, Vec<String>>, ApiResponse> {
+    let mut tags: HashMap<i32, Vec<String>> = HashMap::new();
+
+    let rows = post_tag::Entity::find()
+        .select_only()
+        .column(post_tag::Column::PostId)
+        .column(tag::Column::Name)
+        .join(JoinType::InnerJoin, post_tag::Relation::Tag.def())
+        .filter(post_tag::Column::PostId.is_in(post_ids.to_vec()))
+        .order_by_asc(tag::Column::Name)
+        .into_tuple::<(i32, String)>()
+        .all(db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+    for (post_id, name) in rows {
+        tags.entry(post_id).or_default().push(name);
+    }
+
+    Ok(tags)
+}
+
+async fn tag_counts(
+    db: &DatabaseConnection,
+    prefix: Option<&str>,
+    limit: u64,
+    offset: u64,
+) -> Result<Vec<TagCountOut>, ApiResponse> {
+    let mut select = tag::Entity::find()
+        .select_only()
+        .column(tag::Column::Name)
+        .column_as(post::Column::Id.count(), "post_count")
+        .join(JoinType::InnerJoin, tag::Relation::PostTag.def())
+        .join(JoinType::InnerJoin, post_tag::Relation::Post.def())
+        .filter(publicly_listed());
+
+    if let Some(prefix) = prefix {
+        // Tag names may contain `_`, which LIKE would treat as a wildcard
+        select = select.filter(tag::Column::Name.like(format!("{}%", prefix.replace('_', "\\_"))));
+    }
+
+    let tags = select
+        .group_by(tag::Column::Id)
+        .group_by(tag::Column::Name)
+        .order_by_desc(Expr::cust("post_count"))
+        .order_by_asc(tag::Column::Name)
+        .limit(limit)
+        .offset(offset)
+        .into_tuple::<(String, i64)>()
+        .all(db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?
+        .into_iter()
+        .map(|(name, post_count)| TagCountOut {
+            name,
+            post_count: post_count as u64,
+        })
+        .collect();
+
+    Ok(tags)
+}
+
+fn check_limit(limit: Option<u64>, default: u64) -> Result<u64, ApiResponse> {
+    match limit.unwrap_or(default) {
+        limit if limit > MAX_PAGE_SIZE => Err(ApiResponse::new(
+            400,
+            format!("limit can be at most {}", MAX_PAGE_SIZE),
+        )),
+        limit => Ok(limit),
+    }
+}
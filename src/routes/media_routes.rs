@@ -0,0 +1,14 @@
+use actix_web::{middleware::from_fn, web};
+
+use super::{handlers::media_handlers, middleware};
+
+pub fn config(config: &mut web::ServiceConfig) {
+    config.service(
+        web::scope("/media")
+            // Signed links may be bound to a user
+            .wrap(from_fn(
+                middleware::auth_middleware::optional_auth_middleware,
+            ))
+            .service(media_handlers::get_media),
+    );
+}
@@ -42,3 +42,24 @@ pub async fn check_auth_middleware(
         .await
         .map_err(|err| Error::from(ApiResponse::new(500, err.to_string())))
 }
+
+/// Like [`check_auth_middleware`], but lets requests through anonymously when they come without
+/// an `Authorization` header or its token is invalid or expired. Handlers behind it can take an
+/// `Option<Claims>`.
+pub async fn optional_auth_middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let claim = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|auth| auth.to_str().ok())
+        .and_then(|auth| decode_jwt(auth.replace("Bearer ", "")).ok());
+    if let Some(claim) = claim {
+        req.extensions_mut().insert(claim.claims);
+    }
+
+    next.call(req)
+        .await
+        .map_err(|err| Error::from(ApiResponse::new(500, err.to_string())))
+}
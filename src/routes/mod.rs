@@ -1,6 +1,12 @@
+pub mod admin_routes;
 pub mod auth_routes;
+pub mod bookmark_routes;
+pub mod comment_routes;
+pub mod feed_routes;
 pub mod handlers;
 pub mod home_routes;
+pub mod media_routes;
 pub mod middleware;
 pub mod post_routes;
+pub mod tag_routes;
 pub mod user_routes;
@@ -1,6 +1,12 @@
 use actix_web::{middleware::from_fn, web};
 
-use super::{handlers::post_handlers, middleware};
+use super::{
+    handlers::{
+        analytics_handlers, attachment_handlers, comment_handlers, post_handlers,
+        reaction_handlers, revision_handlers, slug_handlers, tag_handlers,
+    },
+    middleware,
+};
 
 pub fn config(config: &mut web::ServiceConfig) {
     config
@@ -9,11 +15,33 @@ pub fn config(config: &mut web::ServiceConfig) {
             web::scope("/secure/post")
                 .wrap(from_fn(middleware::auth_middleware::check_auth_middleware))
                 .service(post_handlers::create_post)
-                .service(post_handlers::get_my_posts),
+                .service(post_handlers::get_my_posts)
+                .service(post_handlers::update_post)
+                .service(post_handlers::delete_post)
+                .service(post_handlers::restore_post)
+                .service(revision_handlers::get_post_revisions)
+                .service(revision_handlers::diff_post_revisions)
+                .service(revision_handlers::get_post_revision)
+                .service(revision_handlers::restore_post_revision)
+                .service(attachment_handlers::reorder_attachments)
+                .service(attachment_handlers::update_attachment)
+                .service(attachment_handlers::delete_attachment)
+                .service(comment_handlers::create_comment)
+                .service(reaction_handlers::react)
+                .service(reaction_handlers::unreact)
+                .service(analytics_handlers::get_post_analytics),
         )
         .service(
             web::scope("/post")
+                .wrap(from_fn(
+                    middleware::auth_middleware::optional_auth_middleware,
+                ))
                 .service(post_handlers::get_all_posts)
+                .service(post_handlers::search_posts)
+                .service(comment_handlers::get_post_comments)
+                .service(reaction_handlers::get_post_reactions)
+                .service(tag_handlers::get_tag_posts)
+                .service(slug_handlers::get_post_by_slug)
                 .service(post_handlers::get_one_post),
         ); // Unsecure Post Apis
 }
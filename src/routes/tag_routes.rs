@@ -0,0 +1,11 @@
+use actix_web::web;
+
+use super::handlers::tag_handlers;
+
+pub fn config(config: &mut web::ServiceConfig) {
+    config.service(
+        web::scope("/tags")
+            .service(tag_handlers::get_tags)
+            .service(tag_handlers::autocomplete_tags),
+    );
+}
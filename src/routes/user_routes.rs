@@ -1,12 +1,17 @@
 use actix_web::{middleware::from_fn, web};
 
-use super::{handlers::user_handlers, middleware};
+use super::{
+    handlers::{follow_handlers, user_handlers},
+    middleware,
+};
 
 pub fn config(config: &mut web::ServiceConfig) {
     config.service(
         web::scope("/user")
             .wrap(from_fn(middleware::auth_middleware::check_auth_middleware))
             .service(user_handlers::user)
-            .service(user_handlers::update_user),
+            .service(user_handlers::update_user)
+            .service(follow_handlers::follow_user)
+            .service(follow_handlers::unfollow_user),
     );
 }
@@ -0,0 +1,43 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Query string of the analytics of a post. Days are in UTC, both ends are included.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct AnalyticsQuery {
+    /// First day, 29 days before `to` if missing
+    pub from: Option<NaiveDate>,
+    /// Last day, today if missing
+    pub to: Option<NaiveDate>,
+}
+
+/// Views of a post over a range of days. Views of the last minute may not be counted yet.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct PostAnalyticsOut {
+    pub post_uuid: Uuid,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub views: u64,
+    /// Sum of the unique viewers of each day, a viewer coming back on another day counts again
+    pub daily_unique_viewers_sum: u64,
+    /// Every day of the range, oldest first
+    pub days: Vec<DailyViewsOut>,
+    /// Sites linking to the post, most views first
+    pub referrers: Vec<ReferrerOut>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct DailyViewsOut {
+    pub day: NaiveDate,
+    pub views: u64,
+    pub unique_viewers: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct ReferrerOut {
+    /// Host of the referring page, `None` for views without referrer. Hosts past the first 50 of
+    /// a day are counted as `(other)`.
+    pub referrer: Option<String>,
+    pub views: u64,
+}
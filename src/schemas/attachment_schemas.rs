@@ -0,0 +1,85 @@
+use entity::{attachment_thumbnail, post_attachment};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{storage::Storage, utils::media_urls::MediaAccess};
+
+/// An image attached to a post
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct AttachmentOut {
+    pub uuid: Uuid,
+    pub position: i32,
+    pub file_name: String,
+    /// Absolute URL the image is served at. Signed and expiring for posts not everyone may see.
+    pub url: String,
+    pub alt_text: Option<String>,
+    /// Unknown for images uploaded before dimensions were recorded
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    /// Downscaled copies, smallest first. Images smaller than a thumbnail size have none for it.
+    pub thumbnails: Vec<ThumbnailOut>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct ThumbnailOut {
+    /// The configured longest side this thumbnail was made for
+    pub size: i32,
+    pub file_name: String,
+    pub url: String,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl ThumbnailOut {
+    pub(crate) fn new(
+        value: attachment_thumbnail::Model,
+        storage: &dyn Storage,
+        access: MediaAccess,
+    ) -> Self {
+        ThumbnailOut {
+            size: value.size,
+            url: access.url(storage, &value.file_name),
+            file_name: value.file_name,
+            width: value.width,
+            height: value.height,
+        }
+    }
+}
+
+impl AttachmentOut {
+    /// Output form of `value` with its `thumbnails`, linking to the files in `storage` as
+    /// `access` allows
+    pub(crate) fn new(
+        value: post_attachment::Model,
+        thumbnails: Vec<attachment_thumbnail::Model>,
+        storage: &dyn Storage,
+        access: MediaAccess,
+    ) -> Self {
+        AttachmentOut {
+            uuid: value.uuid,
+            position: value.position,
+            url: access.url(storage, &value.file_name),
+            file_name: value.file_name,
+            alt_text: value.alt_text,
+            width: value.width,
+            height: value.height,
+            thumbnails: thumbnails
+                .into_iter()
+                .map(|thumbnail| ThumbnailOut::new(thumbnail, storage, access))
+                .collect(),
+        }
+    }
+}
+
+/// New order of the attachments of a post
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct ReorderAttachments {
+    /// Uuids of all attachments of the post, first one first
+    pub attachments: Vec<Uuid>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct UpdateAttachment {
+    /// `null` removes the alt text
+    pub alt_text: Option<String>,
+}
@@ -0,0 +1,42 @@
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct CreateBookmarkCollection {
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct BookmarkCollectionOut {
+    pub uuid: Uuid,
+    pub name: String,
+    /// The "Saved" collection posts are bookmarked into unless another one is given
+    pub is_default: bool,
+    pub post_count: u64,
+    pub created_at: DateTime<FixedOffset>,
+}
+
+/// Query string choosing a collection, the default one if missing
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct BookmarkQuery {
+    pub collection: Option<Uuid>,
+}
+
+/// Query string of the listing of bookmarked posts, newest bookmark first
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct BookmarkListQuery {
+    /// Collection to list, the default one if missing
+    pub collection: Option<Uuid>,
+    pub limit: Option<u64>,
+    pub cursor: Option<String>,
+}
+
+/// The collections of the authenticated user a post is bookmarked in
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct BookmarkOut {
+    pub post_uuid: Uuid,
+    pub collections: Vec<Uuid>,
+}
@@ -0,0 +1,30 @@
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::schemas::user_schemas::PublicUserOut;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct CreateComment {
+    pub text: String,
+    /// Comment this one replies to. Top level comment if missing.
+    pub parent_uuid: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct UpdateComment {
+    pub text: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct CommentOut {
+    pub uuid: Uuid,
+    pub parent_uuid: Option<Uuid>,
+    /// `None` once the comment is deleted. Deleted comments are kept as long as they have replies.
+    pub text: Option<String>,
+    pub author: Option<PublicUserOut>,
+    pub reply_count: u64,
+    pub created_at: DateTime<FixedOffset>,
+    pub updated_at: Option<DateTime<FixedOffset>>,
+    pub deleted: bool,
+}
@@ -0,0 +1,10 @@
+use serde::{Deserialize, Serialize};
+
+/// Whether the authenticated user follows another one
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct FollowOut {
+    pub user_id: i32,
+    pub following: bool,
+    /// Number of users following `user_id`
+    pub follower_count: u64,
+}
@@ -0,0 +1,51 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Query string of a media sweep
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct MediaSweepQuery {
+    /// Only report what would be removed, `true` unless set to `false`
+    pub dry_run: Option<bool>,
+}
+
+/// Outcome of reconciling the stored files with the database
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct MediaSweepReport {
+    pub dry_run: bool,
+    /// Number of files found in storage
+    pub scanned: usize,
+    /// Stored files no attachment refers to
+    pub orphans: Vec<OrphanOut>,
+    /// Number of orphans removed by this sweep
+    pub removed: usize,
+    /// Attachments whose files are missing from storage
+    pub missing: Vec<MissingMediaOut>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct OrphanOut {
+    pub file_name: String,
+    pub modified: Option<DateTime<Utc>>,
+    /// Whether the file is past the grace period, so it can be removed
+    pub expired: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct MissingMediaOut {
+    pub post_uuid: Uuid,
+    pub file_name: String,
+}
+
+/// Query string of a signed media link. Unknown fields are ignored, clients may add their own
+/// to bust caches.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct SignedMediaQuery {
+    /// Unix time the link expires at
+    pub expires: Option<i64>,
+    /// User the link is valid for, anyone holding it if unset
+    pub user: Option<i32>,
+    /// Hex encoded HMAC of the file name, `expires` and `user`
+    pub signature: Option<String>,
+}
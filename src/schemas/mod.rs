@@ -1,3 +1,13 @@
+pub(crate) mod analytics_schemas;
+pub(crate) mod attachment_schemas;
+pub(crate) mod bookmark_schemas;
+pub(crate) mod comment_schemas;
+pub(crate) mod follow_schemas;
+pub(crate) mod media_schemas;
+pub(crate) mod pagination_schemas;
 pub(crate) mod post_schemas;
+pub(crate) mod reaction_schemas;
+pub(crate) mod revision_schemas;
+pub(crate) mod tag_schemas;
 pub(crate) mod token_schema;
 pub(crate) mod user_schemas;
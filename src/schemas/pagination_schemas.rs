@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+
+/// Query parameters of a cursor paginated listing
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct PageQuery {
+    /// Maximum number of items in the page
+    pub limit: Option<u64>,
+    /// Opaque cursor taken from `next_cursor` or `prev_cursor` of a previous page
+    pub cursor: Option<String>,
+}
+
+/// One page of a cursor paginated listing
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct Page<T> {
+    pub data: Vec<T>,
+    pub next_cursor: Option<String>,
+    pub prev_cursor: Option<String>,
+}
+
+impl<T> Page<T> {
+    pub(crate) fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+        Page {
+            data: self.data.into_iter().map(f).collect(),
+            next_cursor: self.next_cursor,
+            prev_cursor: self.prev_cursor,
+        }
+    }
+}
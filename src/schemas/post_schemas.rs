@@ -1,16 +1,94 @@
-use actix_multipart::form::{tempfile::TempFile, text::Text, MultipartForm};
+use actix_multipart::form::{text::Text, MultipartForm};
 use chrono::{DateTime, FixedOffset};
-use entity::post;
+use entity::{
+    post,
+    sea_orm_active_enums::{PostStatus, PostVisibility},
+};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::schemas::user_schemas::UserOut;
+use crate::{
+    schemas::{
+        attachment_schemas::AttachmentOut, reaction_schemas::ReactionSummary, user_schemas::UserOut,
+    },
+    utils::{markdown::render_markdown, uploads::UploadedFile},
+};
 
 #[derive(Debug, MultipartForm)]
 pub(crate) struct CreatePostModel {
     pub title: Text<String>,
     pub text: Text<String>,
-    pub file: Option<TempFile>,
+    /// Images in their order, the field may be repeated
+    pub file: Vec<UploadedFile>,
+    /// Alt text of each file, in the same order
+    pub alt_text: Vec<Text<String>>,
+    /// Tags separated by commas or whitespace, the field may be repeated. `#hashtags` in the
+    /// text are added on top.
+    pub tags: Vec<Text<String>>,
+    /// `draft`, `scheduled` or `published` (default)
+    pub status: Option<Text<PostStatus>>,
+    /// RFC 3339 time a scheduled post gets published at
+    pub publish_at: Option<Text<DateTime<FixedOffset>>>,
+    /// `public` (default), `unlisted`, `followers` or `private`
+    pub visibility: Option<Text<PostVisibility>>,
+}
+
+#[derive(Debug, MultipartForm)]
+pub(crate) struct UpdatePostModel {
+    pub title: Option<Text<String>>,
+    pub text: Option<Text<String>>,
+    /// Images added after the current ones, the field may be repeated
+    pub file: Vec<UploadedFile>,
+    /// Alt text of each new file, in the same order
+    pub alt_text: Vec<Text<String>>,
+    /// Set to `true` to drop all current images. New files replace them.
+    pub remove_image: Option<Text<bool>>,
+    /// Replaces the explicitly set tags when present, an empty value removes them.
+    /// `#hashtags` in the text are kept in sync either way.
+    pub tags: Vec<Text<String>>,
+    pub status: Option<Text<PostStatus>>,
+    /// RFC 3339 time a scheduled post gets published at
+    pub publish_at: Option<Text<DateTime<FixedOffset>>>,
+    pub visibility: Option<Text<PostVisibility>>,
+}
+
+/// Field a post listing is sorted by
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum PostSortField {
+    #[default]
+    CreatedAt,
+    /// Last modification. Posts which were never edited sort by their creation time.
+    UpdatedAt,
+}
+
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// Query string of the post listings. Unknown parameters are rejected.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct PostListQuery {
+    pub author_id: Option<i32>,
+    pub created_after: Option<DateTime<FixedOffset>>,
+    pub created_before: Option<DateTime<FixedOffset>>,
+    pub has_image: Option<bool>,
+    pub title_contains: Option<String>,
+    /// Public listings only ever contain published posts
+    pub status: Option<PostStatus>,
+    /// Unlisted posts are only ever listed among the own posts
+    pub visibility: Option<PostVisibility>,
+    #[serde(default)]
+    pub sort: PostSortField,
+    #[serde(default)]
+    pub order: SortOrder,
+    pub limit: Option<u64>,
+    pub cursor: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -18,12 +96,52 @@ pub(crate) struct PostOut {
     pub id: i32,
     pub title: String,
     pub text: String,
+    /// `text` rendered from markdown into sanitized HTML
+    pub text_html: String,
     pub uuid: Uuid,
+    /// The post is reachable at `/post/s/{slug}`
+    pub slug: String,
+    /// File name of the first attachment
     pub image: Option<String>,
+    /// Absolute URL of the first attachment
+    pub image_url: Option<String>,
+    pub attachments: Vec<AttachmentOut>,
     pub user_id: i32,
+    pub status: PostStatus,
+    /// Who may see the post besides its author
+    pub visibility: PostVisibility,
     pub created_at: DateTime<FixedOffset>,
     pub updated_at: Option<DateTime<FixedOffset>>,
+    pub published_at: Option<DateTime<FixedOffset>>,
     pub user: Option<UserOut>,
+    pub comment_count: u64,
+    pub tags: Vec<String>,
+    #[serde(flatten)]
+    pub reactions: ReactionSummary,
+    /// Whether the authenticated viewer bookmarked the post, `None` for anonymous viewers
+    pub bookmarked: Option<bool>,
+}
+
+/// Query string of the post search
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct PostSearchQuery {
+    /// Words to search for. `"quoted words"` match a phrase, `word*` matches a prefix.
+    pub q: String,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+}
+
+/// Post matching a search, best matches come first
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct PostSearchOut {
+    #[serde(flatten)]
+    pub post: PostOut,
+    pub rank: f32,
+    /// HTML escaped title with matches wrapped in `<mark>`
+    pub title_highlight: String,
+    /// HTML escaped excerpts of the text with matches wrapped in `<mark>`
+    pub snippet: String,
 }
 
 impl From<post::Model> for PostOut {
@@ -31,13 +149,26 @@ impl From<post::Model> for PostOut {
         PostOut {
             id: value.id,
             title: value.title,
+            text_html: value
+                .text_html
+                .unwrap_or_else(|| render_markdown(&value.text)),
             text: value.text,
             uuid: value.uuid,
-            image: value.image,
+            slug: value.slug,
+            image: None,
+            image_url: None,
+            attachments: Vec::new(),
             user_id: value.user_id,
+            status: value.status,
+            visibility: value.visibility,
             created_at: value.created_at,
             updated_at: value.updated_at,
+            published_at: value.published_at,
             user: None,
+            comment_count: 0,
+            tags: Vec::new(),
+            reactions: ReactionSummary::default(),
+            bookmarked: None,
         }
     }
 }
@@ -0,0 +1,32 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+
+use crate::schemas::user_schemas::PublicUserOut;
+
+/// Query string of the listing of users who reacted to a post
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ReactionListQuery {
+    /// Only list reactions of this kind
+    pub kind: Option<String>,
+    pub limit: Option<u64>,
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct ReactionOut {
+    pub kind: String,
+    pub user: Option<PublicUserOut>,
+    pub created_at: DateTime<FixedOffset>,
+}
+
+/// Reactions on a single post
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub(crate) struct ReactionSummary {
+    /// Number of reactions per kind
+    pub reactions: BTreeMap<String, u64>,
+    /// Kinds the authenticated viewer reacted with, `None` for anonymous viewers
+    pub my_reactions: Option<Vec<String>>,
+}
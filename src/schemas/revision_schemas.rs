@@ -0,0 +1,50 @@
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+
+use crate::schemas::user_schemas::UserOut;
+
+/// A previous version of a post
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct RevisionOut {
+    pub number: i32,
+    pub title: String,
+    pub text: String,
+    /// User whose edit replaced this version
+    pub editor: Option<UserOut>,
+    /// Time this version was replaced
+    pub created_at: DateTime<FixedOffset>,
+}
+
+/// Query string of a diff between two versions of a post
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct RevisionDiffQuery {
+    pub from: i32,
+    /// Defaults to the current version of the post
+    pub to: Option<i32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum DiffOp {
+    Equal,
+    Insert,
+    Delete,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct DiffChange {
+    pub op: DiffOp,
+    pub value: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct RevisionDiffOut {
+    pub from: i32,
+    /// `None` when compared to the current version
+    pub to: Option<i32>,
+    /// Word level changes of the title
+    pub title: Vec<DiffChange>,
+    /// Line level changes of the text
+    pub text: Vec<DiffChange>,
+}
@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+
+/// Query string of the tag listing
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct TagListQuery {
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+}
+
+/// Query string of the tag autocomplete
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct TagAutocompleteQuery {
+    /// Start of the tag name, with or without the leading `#`
+    pub q: String,
+    pub limit: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct TagCountOut {
+    pub name: String,
+    /// Number of publicly listed posts with this tag
+    pub post_count: u64,
+}
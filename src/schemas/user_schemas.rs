@@ -23,6 +23,13 @@ pub(crate) struct LoginUser {
 
 pub(crate) type UserOut = UserUpdate;
 
+/// A user as shown to anyone, such as the author of a comment or someone reacting to a post
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct PublicUserOut {
+    pub id: i32,
+    pub name: String,
+}
+
 impl From<user::Model> for UserUpdate {
     fn from(value: user::Model) -> Self {
         UserUpdate {
@@ -32,3 +39,12 @@ impl From<user::Model> for UserUpdate {
         }
     }
 }
+
+impl From<user::Model> for PublicUserOut {
+    fn from(value: user::Model) -> Self {
+        PublicUserOut {
+            id: value.id,
+            name: value.name,
+        }
+    }
+}
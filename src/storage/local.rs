@@ -0,0 +1,90 @@
+//! Files kept in a directory of the local file system
+
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+
+use super::{media_route_url, Storage, StorageError, StoredFile};
+use crate::utils::constants::get_storage_dir;
+
+/// Files in the directory `STORAGE_DIR`, `./public` by default. Only fit for a single instance or
+/// several sharing a network file system.
+#[derive(Debug)]
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    /// Storage in `root`, which is created if missing
+    pub fn new(root: impl AsRef<Path>) -> Result<Self, StorageError> {
+        std::fs::create_dir_all(root.as_ref())?;
+        Ok(LocalStorage {
+            root: root.as_ref().to_path_buf(),
+        })
+    }
+
+    pub fn from_env() -> Result<Self, StorageError> {
+        LocalStorage::new(get_storage_dir())
+    }
+
+    fn path(&self, name: &str) -> Result<PathBuf, StorageError> {
+        // Names are single path components, nothing may reach out of the root
+        if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+            return Err(StorageError {
+                message: format!("Invalid file name {}", name),
+            });
+        }
+        Ok(self.root.join(name))
+    }
+}
+
+impl Storage for LocalStorage {
+    fn put(&self, name: &str, bytes: Vec<u8>, _content_type: &str) -> Result<(), StorageError> {
+        Ok(std::fs::write(self.path(name)?, bytes)?)
+    }
+
+    fn get(&self, name: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        match std::fs::read(self.path(name)?) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn delete(&self, name: &str) -> Result<(), StorageError> {
+        match std::fs::remove_file(self.path(name)?) {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn url(&self, name: &str) -> String {
+        media_route_url(name)
+    }
+
+    fn list(&self) -> Result<Vec<StoredFile>, StorageError> {
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(&self.root)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+            if !metadata.is_file() || name.starts_with('.') {
+                continue;
+            }
+            files.push(StoredFile {
+                name,
+                modified: metadata.modified().ok().map(DateTime::<Utc>::from),
+            });
+        }
+        Ok(files)
+    }
+
+    fn local_path(&self, name: &str) -> Option<PathBuf> {
+        self.path(name).ok()
+    }
+}
@@ -0,0 +1,101 @@
+//! Where uploaded files are kept
+
+use std::{fmt::Display, path::PathBuf, sync::Arc};
+
+use chrono::{DateTime, Utc};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+
+use crate::utils::constants::{get_public_base_url, get_storage_backend};
+
+pub mod local;
+#[cfg(feature = "s3")]
+pub mod s3;
+
+/// Characters left as they are in URLs of stored files
+const URL_SAFE: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+/// A place to keep uploaded files in, addressed by their file name. Calls block, so async code
+/// with more to do than a single upload should run them on a blocking thread.
+pub trait Storage: std::fmt::Debug + Send + Sync {
+    /// Store `bytes` under `name`, replacing what was stored under it before
+    fn put(&self, name: &str, bytes: Vec<u8>, content_type: &str) -> Result<(), StorageError>;
+
+    /// Content stored under `name`, `None` if there is nothing
+    fn get(&self, name: &str) -> Result<Option<Vec<u8>>, StorageError>;
+
+    /// Remove what is stored under `name`. Removing a missing file is no error.
+    fn delete(&self, name: &str) -> Result<(), StorageError>;
+
+    /// Absolute URL clients load `name` from
+    fn url(&self, name: &str) -> String;
+
+    /// Every file kept in the storage
+    fn list(&self) -> Result<Vec<StoredFile>, StorageError>;
+
+    /// Where `name` is on the local file system, for storages keeping files there. Such files are
+    /// served straight from disk.
+    fn local_path(&self, _name: &str) -> Option<PathBuf> {
+        None
+    }
+}
+
+/// A file kept in a storage
+#[derive(Debug, Clone)]
+pub struct StoredFile {
+    pub name: String,
+    /// When the file was last put, if the storage knows
+    pub modified: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug)]
+pub struct StorageError {
+    pub message: String,
+}
+
+impl Display for StorageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Storage error: {}", self.message)
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+impl From<std::io::Error> for StorageError {
+    fn from(value: std::io::Error) -> Self {
+        StorageError {
+            message: value.to_string(),
+        }
+    }
+}
+
+/// The storage backend selected by `STORAGE_BACKEND`, `local` by default. The `s3` backend needs
+/// the crate to be built with the `s3` feature.
+pub fn from_config() -> Result<Arc<dyn Storage>, StorageError> {
+    match get_storage_backend().as_str() {
+        "local" => Ok(Arc::new(local::LocalStorage::from_env()?)),
+        #[cfg(feature = "s3")]
+        "s3" => Ok(Arc::new(s3::S3Storage::from_env()?)),
+        #[cfg(not(feature = "s3"))]
+        "s3" => Err(StorageError {
+            message: "The s3 storage backend needs the `s3` feature enabled at build time"
+                .to_string(),
+        }),
+        backend => Err(StorageError {
+            message: format!("Unknown storage backend {}", backend),
+        }),
+    }
+}
+
+/// URL of `name` on the media route of this API, which serves files of any backend
+pub(crate) fn media_route_url(name: &str) -> String {
+    format!("{}/media/{}", get_public_base_url(), encode_name(name))
+}
+
+/// `name` percent encoded for use as a single URL path segment
+pub(crate) fn encode_name(name: &str) -> String {
+    utf8_percent_encode(name, URL_SAFE).to_string()
+}
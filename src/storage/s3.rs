@@ -0,0 +1,166 @@
+//! Files kept in a bucket of an S3 compatible object storage, such as AWS S3 or MinIO
+
+use std::{env, future::Future, time::Duration};
+
+use object_store::{
+    aws::{AmazonS3, AmazonS3Builder},
+    path::Path,
+    Attribute, Attributes, ClientOptions, ObjectStore, PutOptions, PutPayload,
+};
+use tokio::runtime::{self, Runtime};
+
+use super::{encode_name, media_route_url, Storage, StorageError, StoredFile};
+
+/// Longest time to wait for a connection to the storage
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Longest time to wait for the storage to answer a request
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Objects in a bucket, addressed path style as `{endpoint}/{bucket}/{name}` so no DNS setup is
+/// needed for local setups.
+///
+/// Configured by:
+/// - `S3_ENDPOINT`, such as `http://localhost:9000`
+/// - `S3_BUCKET`
+/// - `S3_REGION`, `us-east-1` by default
+/// - `S3_ACCESS_KEY_ID` and `S3_SECRET_ACCESS_KEY`
+/// - `S3_PUBLIC_URL`, base URL the objects can be read from by anyone. Without it files are
+///   served through the media route of this API.
+#[derive(Debug)]
+pub struct S3Storage {
+    store: AmazonS3,
+    /// Drives the requests of the client, which keeps its connections open between them. Only
+    /// `None` while being dropped.
+    runtime: Option<Runtime>,
+    public_url: Option<String>,
+}
+
+impl S3Storage {
+    pub fn from_env() -> Result<Self, StorageError> {
+        let required = |name: &str| {
+            env::var(name).map_err(|_| StorageError {
+                message: format!("{} is needed for the s3 storage backend", name),
+            })
+        };
+        let endpoint = required("S3_ENDPOINT")?;
+
+        let store = AmazonS3Builder::new()
+            .with_endpoint(endpoint.trim_end_matches('/'))
+            .with_virtual_hosted_style_request(false)
+            .with_bucket_name(required("S3_BUCKET")?)
+            .with_region(env::var("S3_REGION").unwrap_or("us-east-1".to_string()))
+            .with_access_key_id(required("S3_ACCESS_KEY_ID")?)
+            .with_secret_access_key(required("S3_SECRET_ACCESS_KEY")?)
+            .with_client_options(
+                ClientOptions::new()
+                    .with_allow_http(endpoint.starts_with("http://"))
+                    .with_connect_timeout(CONNECT_TIMEOUT)
+                    .with_timeout(TIMEOUT),
+            )
+            .build()
+            .map_err(storage_error)?;
+        let runtime = runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("s3-storage")
+            .enable_all()
+            .build()?;
+
+        Ok(S3Storage {
+            store,
+            runtime: Some(runtime),
+            public_url: env::var("S3_PUBLIC_URL")
+                .ok()
+                .map(|url| url.trim_end_matches('/').to_string()),
+        })
+    }
+
+    /// Wait for `request` to the storage to finish
+    fn block_on<T>(&self, request: impl Future<Output = T>) -> T {
+        self.runtime
+            .as_ref()
+            .expect("The runtime is only taken when dropping the storage")
+            .block_on(request)
+    }
+}
+
+impl Drop for S3Storage {
+    fn drop(&mut self) {
+        // Dropping a runtime blocks, which isn't allowed in async code
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+impl Storage for S3Storage {
+    fn put(&self, name: &str, bytes: Vec<u8>, content_type: &str) -> Result<(), StorageError> {
+        let options = PutOptions {
+            attributes: Attributes::from_iter([(Attribute::ContentType, content_type.to_string())]),
+            ..Default::default()
+        };
+        self.block_on(
+            self.store
+                .put_opts(&object_path(name)?, PutPayload::from(bytes), options),
+        )
+        .map(|_| ())
+        .map_err(storage_error)
+    }
+
+    fn get(&self, name: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        let path = object_path(name)?;
+        self.block_on(async {
+            match self.store.get(&path).await {
+                Ok(object) => object.bytes().await.map(|bytes| Some(bytes.to_vec())),
+                Err(object_store::Error::NotFound {
+                    ..
+                }) => Ok(None),
+                Err(err) => Err(err),
+            }
+        })
+        .map_err(storage_error)
+    }
+
+    fn delete(&self, name: &str) -> Result<(), StorageError> {
+        match self.block_on(self.store.delete(&object_path(name)?)) {
+            Ok(())
+            | Err(object_store::Error::NotFound {
+                ..
+            }) => Ok(()),
+            Err(err) => Err(storage_error(err)),
+        }
+    }
+
+    fn url(&self, name: &str) -> String {
+        match &self.public_url {
+            Some(public_url) => format!("{}/{}", public_url, encode_name(name)),
+            None => media_route_url(name),
+        }
+    }
+
+    fn list(&self) -> Result<Vec<StoredFile>, StorageError> {
+        let listing = self
+            .block_on(self.store.list_with_delimiter(None))
+            .map_err(storage_error)?;
+        Ok(listing
+            .objects
+            .into_iter()
+            .map(|object| StoredFile {
+                name: object.location.to_string(),
+                modified: Some(object.last_modified),
+            })
+            .collect())
+    }
+}
+
+/// Key of the object stored as `name`, which is used as it is
+fn object_path(name: &str) -> Result<Path, StorageError> {
+    Path::parse(name).map_err(|err| StorageError {
+        message: format!("Invalid file name {}: {}", name, err),
+    })
+}
+
+fn storage_error(err: object_store::Error) -> StorageError {
+    StorageError {
+        message: err.to_string(),
+    }
+}
@@ -0,0 +1,150 @@
+//! Background task writing the post views counted in memory to the daily counters
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time,
+};
+
+use actix_web::rt;
+use chrono::NaiveDate;
+use entity::{post, post_referrer_daily, post_view_daily};
+use sea_orm::{
+    sea_query::{Alias, Expr, OnConflict},
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QuerySelect, Set,
+    TransactionTrait,
+};
+
+use crate::utils::{
+    constants::get_view_flush_interval_secs,
+    views::{PendingViews, ViewCounter},
+};
+
+/// Rows written per statement, keeping well below the limit of bind parameters
+const BATCH_SIZE: usize = 1000;
+
+/// Periodically write the counted views. Runs forever, meant to be spawned at startup.
+pub async fn run(db: DatabaseConnection, views: Arc<ViewCounter>) {
+    let mut interval =
+        rt::time::interval(time::Duration::from_secs(get_view_flush_interval_secs()));
+    loop {
+        interval.tick().await;
+        flush_views(&db, &views).await;
+    }
+}
+
+/// Write the views counted since the last flush. They are kept for the next one if that fails.
+pub async fn flush_views(db: &DatabaseConnection, views: &ViewCounter) {
+    let pending = views.take();
+    if pending.is_empty() {
+        return;
+    }
+    if let Err(err) = write_views(db, &pending).await {
+        log::error!("Writing post views failed: {}", err);
+        views.restore(pending);
+    }
+}
+
+/// Add `pending` to the daily counters
+async fn write_views(
+    db: &DatabaseConnection,
+    pending: &HashMap<(i32, NaiveDate), PendingViews>,
+) -> Result<(), DbErr> {
+    // Views of posts purged in the meantime are dropped
+    let post_ids: HashSet<i32> = pending.keys().map(|(post_id, _)| *post_id).collect();
+    let existing: HashSet<i32> = post::Entity::find()
+        .select_only()
+        .column(post::Column::Id)
+        .filter(post::Column::Id.is_in(post_ids))
+        .into_tuple::<i32>()
+        .all(db)
+        .await?
+        .into_iter()
+        .collect();
+    let pending: Vec<(&(i32, NaiveDate), &PendingViews)> = pending
+        .iter()
+        .filter(|((post_id, _), _)| existing.contains(post_id))
+        .collect();
+
+    let daily: Vec<post_view_daily::ActiveModel> = pending
+        .iter()
+        .map(|((post_id, day), views)| post_view_daily::ActiveModel {
+            post_id: Set(*post_id),
+            day: Set(*day),
+            views: Set(views.views),
+            unique_viewers: Set(views.unique_viewers),
+        })
+        .collect();
+    let referrers: Vec<post_referrer_daily::ActiveModel> = pending
+        .iter()
+        .flat_map(|((post_id, day), views)| {
+            views
+                .referrers
+                .iter()
+                .map(|(referrer, views)| post_referrer_daily::ActiveModel {
+                    post_id: Set(*post_id),
+                    day: Set(*day),
+                    referrer: Set(referrer.clone()),
+                    views: Set(*views),
+                })
+        })
+        .collect();
+
+    // Counters add up what was written before, `excluded` holds the row which was to be inserted
+    let excluded = Alias::new("excluded");
+    let txn = db.begin().await?;
+    for batch in daily.chunks(BATCH_SIZE) {
+        post_view_daily::Entity::insert_many(batch.to_vec())
+            .on_conflict(
+                OnConflict::columns([
+                    post_view_daily::Column::PostId,
+                    post_view_daily::Column::Day,
+                ])
+                .value(
+                    post_view_daily::Column::Views,
+                    Expr::col((post_view_daily::Entity, post_view_daily::Column::Views)).add(
+                        Expr::col((excluded.clone(), post_view_daily::Column::Views)),
+                    ),
+                )
+                .value(
+                    post_view_daily::Column::UniqueViewers,
+                    Expr::col((
+                        post_view_daily::Entity,
+                        post_view_daily::Column::UniqueViewers,
+                    ))
+                    .add(Expr::col((
+                        excluded.clone(),
+                        post_view_daily::Column::UniqueViewers,
+                    ))),
+                )
+                .to_owned(),
+            )
+            .exec_without_returning(&txn)
+            .await?;
+    }
+    for batch in referrers.chunks(BATCH_SIZE) {
+        post_referrer_daily::Entity::insert_many(batch.to_vec())
+            .on_conflict(
+                OnConflict::columns([
+                    post_referrer_daily::Column::PostId,
+                    post_referrer_daily::Column::Day,
+                    post_referrer_daily::Column::Referrer,
+                ])
+                .value(
+                    post_referrer_daily::Column::Views,
+                    Expr::col((
+                        post_referrer_daily::Entity,
+                        post_referrer_daily::Column::Views,
+                    ))
+                    .add(Expr::col((
+                        excluded.clone(),
+                        post_referrer_daily::Column::Views,
+                    ))),
+                )
+                .to_owned(),
+            )
+            .exec_without_returning(&txn)
+            .await?;
+    }
+    txn.commit().await
+}
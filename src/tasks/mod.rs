@@ -0,0 +1,5 @@
+pub mod flush_views;
+pub mod publish_scheduled;
+pub mod purge_posts;
+pub mod render_markdown;
+pub mod sweep_media;
@@ -0,0 +1,67 @@
+//! Background task publishing scheduled posts once their publication time is reached
+
+use std::time;
+
+use actix_web::rt;
+use chrono::{DateTime, FixedOffset, Utc};
+use entity::{post, sea_orm_active_enums::PostStatus};
+use sea_orm::{
+    sea_query::Expr, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+};
+
+use crate::utils::constants::get_publish_scheduler_interval_secs;
+
+/// Publish due posts, then sleep until the next one is due, but never longer than the
+/// configured interval so posts scheduled in the meantime are picked up. Runs forever, meant
+/// to be spawned at startup.
+pub async fn run(db: DatabaseConnection) {
+    let max_sleep = time::Duration::from_secs(get_publish_scheduler_interval_secs());
+    loop {
+        match publish_due_posts(&db).await {
+            Ok(0) => (),
+            Ok(published) => log::info!("Published {} scheduled posts", published),
+            Err(err) => log::error!("Publishing scheduled posts failed: {}", err),
+        }
+
+        let sleep = match next_publication(&db).await {
+            Ok(Some(publish_at)) => (publish_at.with_timezone(&Utc) - Utc::now())
+                .to_std()
+                .unwrap_or_default()
+                .min(max_sleep),
+            Ok(None) => max_sleep,
+            Err(err) => {
+                log::error!("Looking up scheduled posts failed: {}", err);
+                max_sleep
+            },
+        };
+        rt::time::sleep(sleep).await;
+    }
+}
+
+/// Flip scheduled posts whose publication time has passed to published.
+/// Returns the number of published posts.
+pub async fn publish_due_posts(db: &DatabaseConnection) -> Result<u64, DbErr> {
+    let now = Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap());
+    let result = post::Entity::update_many()
+        .col_expr(
+            post::Column::Status,
+            post::Column::Status.save_as(Expr::val(PostStatus::Published)),
+        )
+        .filter(post::Column::Status.eq(PostStatus::Scheduled))
+        .filter(post::Column::PublishedAt.lte(now))
+        .exec(db)
+        .await?;
+
+    Ok(result.rows_affected)
+}
+
+/// Publication time of the next scheduled post, if there is one
+async fn next_publication(db: &DatabaseConnection) -> Result<Option<DateTime<FixedOffset>>, DbErr> {
+    let next = post::Entity::find()
+        .filter(post::Column::Status.eq(PostStatus::Scheduled))
+        .order_by_asc(post::Column::PublishedAt)
+        .one(db)
+        .await?;
+
+    Ok(next.and_then(|post| post.published_at))
+}
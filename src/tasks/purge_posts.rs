@@ -0,0 +1,60 @@
+//! Background task removing soft deleted posts once their restore window is over
+
+use std::{sync::Arc, time};
+
+use actix_web::rt;
+use chrono::{Duration, Utc};
+use entity::{post, post_attachment};
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, ModelTrait, QueryFilter, TransactionTrait,
+};
+
+use crate::{
+    routes::handlers::attachment_handlers::attachment_files,
+    storage::Storage,
+    utils::{
+        constants::{get_post_restore_window_hours, get_purge_interval_secs},
+        media::{release_media, remove_unreferenced},
+    },
+};
+
+/// Periodically purge soft deleted posts. Runs forever, meant to be spawned at startup.
+pub async fn run(db: DatabaseConnection, storage: Arc<dyn Storage>) {
+    let mut interval = rt::time::interval(time::Duration::from_secs(get_purge_interval_secs()));
+    loop {
+        interval.tick().await;
+        match purge_deleted_posts(&db, &storage).await {
+            Ok(0) => (),
+            Ok(purged) => log::info!("Purged {} deleted posts", purged),
+            Err(err) => log::error!("Purging deleted posts failed: {}", err),
+        }
+    }
+}
+
+/// Delete rows of posts whose restore window has expired along with their image files.
+/// Returns the number of purged posts.
+pub async fn purge_deleted_posts(
+    db: &DatabaseConnection,
+    storage: &Arc<dyn Storage>,
+) -> Result<u64, DbErr> {
+    let cutoff = Utc::now() - Duration::hours(get_post_restore_window_hours());
+    let expired = post::Entity::find()
+        .filter(post::Column::DeletedAt.lt(cutoff))
+        .all(db)
+        .await?;
+
+    let mut purged = 0;
+    for post in expired {
+        let txn = db.begin().await?;
+        let file_names =
+            attachment_files(&txn, post_attachment::Column::PostId.eq(post.id)).await?;
+        // Attachments go along with the post
+        post.delete(&txn).await?;
+        release_media(&txn, file_names.iter().map(String::as_str)).await?;
+        remove_unreferenced(&txn, storage, file_names.iter().map(String::as_str)).await?;
+        txn.commit().await?;
+        purged += 1;
+    }
+
+    Ok(purged)
+}
@@ -0,0 +1,51 @@
+//! Background task rendering the HTML of posts written before it was cached
+
+use entity::post;
+use sea_orm::{
+    sea_query::Expr, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect,
+};
+
+use crate::utils::markdown::render_markdown;
+
+const BATCH_SIZE: u64 = 100;
+
+/// Fill in the cached HTML of every post missing it. Meant to be spawned at startup, posts
+/// without cached HTML are rendered on the fly until it is done.
+pub async fn run(db: DatabaseConnection) {
+    match render_missing_html(&db).await {
+        Ok(0) => (),
+        Ok(rendered) => log::info!("Rendered the HTML of {} posts", rendered),
+        Err(err) => log::error!("Rendering the HTML of posts failed: {}", err),
+    }
+}
+
+/// Render posts without cached HTML in batches. Returns the number of rendered posts.
+pub async fn render_missing_html(db: &DatabaseConnection) -> Result<u64, DbErr> {
+    let mut rendered = 0;
+    loop {
+        let posts = post::Entity::find()
+            .filter(post::Column::TextHtml.is_null())
+            .order_by_asc(post::Column::Id)
+            .limit(BATCH_SIZE)
+            .all(db)
+            .await?;
+        if posts.is_empty() {
+            return Ok(rendered);
+        }
+
+        for post in posts {
+            // Saving the post in the meantime rendered it already, with the text it has now
+            let updated = post::Entity::update_many()
+                .col_expr(
+                    post::Column::TextHtml,
+                    Expr::value(render_markdown(&post.text)),
+                )
+                .filter(post::Column::Id.eq(post.id))
+                .filter(post::Column::TextHtml.is_null())
+                .exec(db)
+                .await?;
+            rendered += updated.rows_affected;
+        }
+    }
+}
@@ -0,0 +1,298 @@
+//! Background task reconciling stored files with the attachments referring to them
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time,
+};
+
+use actix_web::{rt, web};
+use chrono::{Duration, Utc};
+use entity::{attachment_thumbnail, media, post, post_attachment};
+use sea_orm::{
+    sea_query::Expr, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect, TransactionTrait,
+};
+use uuid::Uuid;
+
+use crate::{
+    schemas::media_schemas::{MediaSweepReport, MissingMediaOut, OrphanOut},
+    storage::Storage,
+    utils::{
+        api_response::ApiResponse,
+        constants::{
+            get_media_orphan_grace_hours, get_media_sweep_delete, get_media_sweep_interval_secs,
+        },
+        media::{claim_unreferenced, remove_claimed},
+    },
+};
+
+/// Periodically sweep the storage for orphaned files. They are only removed with
+/// `MEDIA_SWEEP_DELETE` set. Runs forever, meant to be spawned at startup.
+pub async fn run(db: DatabaseConnection, storage: Arc<dyn Storage>) {
+    let mut interval =
+        rt::time::interval(time::Duration::from_secs(get_media_sweep_interval_secs()));
+    loop {
+        interval.tick().await;
+        match sweep_media(&db, storage.clone(), !get_media_sweep_delete()).await {
+            Ok(report) => {
+                if !report.orphans.is_empty() || !report.missing.is_empty() {
+                    log::info!(
+                        "Media sweep found {} orphaned files, removed {} and found {} missing",
+                        report.orphans.len(),
+                        report.removed,
+                        report.missing.len()
+                    );
+                }
+                for missing in &report.missing {
+                    log::warn!(
+                        "Post {} refers to the missing file {}",
+                        missing.post_uuid,
+                        missing.file_name
+                    );
+                }
+            },
+            Err(err) => log::error!("Sweeping media failed: {}", err),
+        }
+    }
+}
+
+/// Compare the files in `storage` with the attachments in the database. Files nothing refers to
+/// are orphans, removed unless `dry_run` once they are older than the grace period. Attachments
+/// whose files are gone are reported as missing.
+pub(crate) async fn sweep_media(
+    db: &DatabaseConnection,
+    storage: Arc<dyn Storage>,
+    dry_run: bool,
+) -> Result<MediaSweepReport, ApiResponse> {
+    // Listing before reading the references makes uploads done in between count as referenced
+    let files = web::block({
+        let storage = storage.clone();
+        move || storage.list()
+    })
+    .await
+    .map_err(|err| ApiResponse::new(500, err.to_string()))?
+    .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+    let mut references: Vec<(i32, String)> = post_attachment::Entity::find()
+        .select_only()
+        .column(post_attachment::Column::PostId)
+        .column(post_attachment::Column::FileName)
+        .into_tuple()
+        .all(db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+    references.extend(
+        attachment_thumbnail::Entity::find()
+            .select_only()
+            .column(post_attachment::Column::PostId)
+            .column(attachment_thumbnail::Column::FileName)
+            .inner_join(post_attachment::Entity)
+            .into_tuple::<(i32, String)>()
+            .all(db)
+            .await
+            .map_err(|err| ApiResponse::new(500, err.to_string()))?,
+    );
+    let referenced: HashSet<&str> = references
+        .iter()
+        .map(|(_, file_name)| file_name.as_str())
+        .collect();
+
+    let cutoff = Utc::now() - Duration::hours(get_media_orphan_grace_hours());
+    let orphans: Vec<OrphanOut> = files
+        .iter()
+        .filter(|file| !referenced.contains(file.name.as_str()))
+        .map(|file| OrphanOut {
+            file_name: file.name.clone(),
+            modified: file.modified,
+            // Files of unknown age are left alone
+            expired: file.modified.is_some_and(|modified| modified < cutoff),
+        })
+        .collect();
+
+    let stored: HashSet<&str> = files.iter().map(|file| file.name.as_str()).collect();
+    let missing: Vec<(i32, String)> = references
+        .iter()
+        .filter(|(_, file_name)| !stored.contains(file_name.as_str()))
+        .cloned()
+        .collect();
+    let post_uuids: HashMap<i32, Uuid> = match missing.is_empty() {
+        true => HashMap::new(),
+        false => post::Entity::find()
+            .select_only()
+            .column(post::Column::Id)
+            .column(post::Column::Uuid)
+            .filter(post::Column::Id.is_in(missing.iter().map(|(post_id, _)| *post_id)))
+            .into_tuple::<(i32, Uuid)>()
+            .all(db)
+            .await
+            .map_err(|err| ApiResponse::new(500, err.to_string()))?
+            .into_iter()
+            .collect(),
+    };
+
+    let expired: Vec<String> = orphans
+        .iter()
+        .filter(|orphan| orphan.expired)
+        .map(|orphan| orphan.file_name.clone())
+        .collect();
+    let removed = match dry_run || expired.is_empty() {
+        true => 0,
+        false => remove_orphans(db, &storage, &expired)
+            .await
+            .map_err(|err| ApiResponse::new(500, err.to_string()))?,
+    };
+
+    Ok(MediaSweepReport {
+        dry_run,
+        scanned: files.len(),
+        orphans,
+        removed,
+        missing: missing
+            .into_iter()
+            .filter_map(|(post_id, file_name)| {
+                Some(MissingMediaOut {
+                    post_uuid: *post_uuids.get(&post_id)?,
+                    file_name,
+                })
+            })
+            .collect(),
+    })
+}
+
+/// Remove the files among `expired` nothing refers to anymore along with their rows. Returns the
+/// number of removed files.
+async fn remove_orphans(
+    db: &DatabaseConnection,
+    storage: &Arc<dyn Storage>,
+    expired: &[String],
+) -> Result<usize, DbErr> {
+    let txn = db.begin().await?;
+    // Uploads of these files from now on wait for the sweep to finish
+    media::Entity::find()
+        .select_only()
+        .column(media::Column::FileName)
+        .filter(media::Column::FileName.is_in(expired.iter().cloned()))
+        .order_by_asc(media::Column::FileName)
+        .lock_exclusive()
+        .into_tuple::<String>()
+        .all(&txn)
+        .await?;
+
+    // Files attached since they were listed aren't orphans anymore
+    let mut attached: HashSet<String> = post_attachment::Entity::find()
+        .select_only()
+        .column(post_attachment::Column::FileName)
+        .filter(post_attachment::Column::FileName.is_in(expired.iter().cloned()))
+        .into_tuple::<String>()
+        .all(&txn)
+        .await?
+        .into_iter()
+        .collect();
+    attached.extend(
+        attachment_thumbnail::Entity::find()
+            .select_only()
+            .column(attachment_thumbnail::Column::FileName)
+            .filter(attachment_thumbnail::Column::FileName.is_in(expired.iter().cloned()))
+            .into_tuple::<String>()
+            .all(&txn)
+            .await?,
+    );
+    let unattached: Vec<&str> = expired
+        .iter()
+        .map(String::as_str)
+        .filter(|file_name| !attached.contains(*file_name))
+        .collect();
+    if unattached.is_empty() {
+        return Ok(0);
+    }
+
+    // Counts left over by orphans, such as after editing the database by hand, would keep them
+    media::Entity::update_many()
+        .col_expr(media::Column::RefCount, Expr::value(0))
+        .filter(media::Column::FileName.is_in(unattached.iter().copied()))
+        .filter(media::Column::RefCount.gt(0))
+        .exec(&txn)
+        .await?;
+    let orphans = claim_unreferenced(&txn, unattached).await?;
+    let removed = orphans.len();
+    remove_claimed(&txn, storage, orphans).await?;
+    txn.commit().await?;
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, sync::Mutex};
+
+    use migration::{Migrator, MigratorTrait};
+    use sea_orm::{ActiveModelTrait, Database, Set};
+
+    use super::*;
+    use crate::storage::{StorageError, StoredFile};
+
+    /// Storage keeping nothing but the names of deleted files
+    #[derive(Debug, Default)]
+    struct DeletedFiles(Mutex<Vec<String>>);
+
+    impl Storage for DeletedFiles {
+        fn put(
+            &self,
+            _name: &str,
+            _bytes: Vec<u8>,
+            _content_type: &str,
+        ) -> Result<(), StorageError> {
+            Ok(())
+        }
+
+        fn get(&self, _name: &str) -> Result<Option<Vec<u8>>, StorageError> {
+            Ok(None)
+        }
+
+        fn delete(&self, name: &str) -> Result<(), StorageError> {
+            self.0.lock().unwrap().push(name.to_string());
+            Ok(())
+        }
+
+        fn url(&self, _name: &str) -> String {
+            String::new()
+        }
+
+        fn list(&self) -> Result<Vec<StoredFile>, StorageError> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a Postgres database in TEST_DATABASE_URL"]
+    async fn removes_orphans_with_stale_counts() {
+        let db = Database::connect(env::var("TEST_DATABASE_URL").unwrap())
+            .await
+            .unwrap();
+        Migrator::up(&db, None).await.unwrap();
+        let file_name = format!("{}-stale.png", Uuid::new_v4());
+        media::ActiveModel {
+            file_name: Set(file_name.clone()),
+            ref_count: Set(2),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        let deleted = Arc::new(DeletedFiles::default());
+        let storage: Arc<dyn Storage> = deleted.clone();
+
+        let removed = remove_orphans(&db, &storage, &[file_name.clone()])
+            .await
+            .unwrap();
+
+        assert_eq!(removed, 1);
+        assert_eq!(*deleted.0.lock().unwrap(), [file_name.clone()]);
+        let row = media::Entity::find()
+            .filter(media::Column::FileName.eq(file_name))
+            .one(&db)
+            .await
+            .unwrap();
+        assert!(row.is_none());
+    }
+}
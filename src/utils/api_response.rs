@@ -4,7 +4,10 @@ use std::fmt::Display;
 
 use actix_web::{
     body::BoxBody,
-    http::{header::ContentType, StatusCode},
+    http::{
+        header::{self, ContentType},
+        StatusCode,
+    },
     web, HttpResponse, Responder, ResponseError,
 };
 use serde_json::json;
@@ -16,6 +19,7 @@ pub struct ApiResponse {
     pub status_code: u16,
     pub body: String,
     response_code: StatusCode,
+    location: Option<String>,
 }
 
 impl ApiResponse {
@@ -24,6 +28,7 @@ impl ApiResponse {
             status_code,
             body,
             response_code: StatusCode::from_u16(status_code).unwrap_or_default(),
+            location: None,
         }
     }
 
@@ -32,6 +37,17 @@ impl ApiResponse {
             status_code,
             body: json!({"message": body}).to_string(),
             response_code: StatusCode::from_u16(status_code).unwrap_or_default(),
+            location: None,
+        }
+    }
+
+    /// Redirect to `location`, with a json message for clients not following it
+    pub fn redirect(status_code: u16, location: String) -> Self {
+        ApiResponse {
+            status_code,
+            body: json!({"message": "Moved", "location": location}).to_string(),
+            response_code: StatusCode::from_u16(status_code).unwrap_or_default(),
+            location: Some(location),
         }
     }
 
@@ -42,6 +58,7 @@ impl ApiResponse {
             status_code,
             body,
             response_code: StatusCode::from_u16(status_code).unwrap_or_default(),
+            location: None,
         })
     }
 }
@@ -62,9 +79,11 @@ impl Responder for ApiResponse {
 
     fn respond_to(self, req: &actix_web::HttpRequest) -> HttpResponse<Self::Body> {
         let body = BoxBody::new(web::BytesMut::from(self.body.as_bytes()));
-        HttpResponse::build(self.response_code)
-            .insert_header(ContentType::json())
-            .body(body)
+        let mut response = HttpResponse::build(self.response_code);
+        if let Some(location) = self.location {
+            response.insert_header((header::LOCATION, location));
+        }
+        response.insert_header(ContentType::json()).body(body)
     }
 }
 
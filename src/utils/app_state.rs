@@ -1,6 +1,15 @@
+use std::sync::Arc;
+
 use sea_orm::DatabaseConnection;
 
+use super::views::ViewCounter;
+use crate::storage::Storage;
+
 #[derive(Debug)]
 pub struct AppState {
     pub db: DatabaseConnection,
+    /// Where uploaded files are kept
+    pub storage: Arc<dyn Storage>,
+    /// Post views not yet written to the database
+    pub views: Arc<ViewCounter>,
 }
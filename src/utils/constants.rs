@@ -1,6 +1,10 @@
 use std::env;
 use std::sync::OnceLock;
 
+/// Longest period, in hours, accepted for the hour settings. 100 years, well within what
+/// `chrono::Duration` can hold.
+const MAX_HOURS: i64 = 100 * 365 * 24;
+
 pub fn get_address() -> &'static String {
     static ADDRESS: OnceLock<String> = OnceLock::new();
     ADDRESS.get_or_init(|| env::var("ADDRESS").unwrap_or("127.0.0.1".to_string()))
@@ -26,12 +30,260 @@ pub fn get_secret() -> &'static String {
     SECRET.get_or_init(|| env::var("SECRET").expect("A secret needs to be defined in Environment"))
 }
 
+/// Most bytes a single uploaded file may have, `MAX_FILE_SIZE`. 10 MiB by default.
 pub fn get_max_file_size() -> u64 {
     static MAX_FILE_SIZE: OnceLock<u64> = OnceLock::new();
     *MAX_FILE_SIZE.get_or_init(|| {
         env::var("MAX_FILE_SIZE")
-            .unwrap()
-            .parse::<u64>()
+            .ok()
+            .and_then(|size| size.parse::<u64>().ok())
             .unwrap_or(10485760)
     })
 }
+
+/// Most bytes of the text fields of a form kept in memory together, `MAX_FORM_MEMORY`. 2 MiB by
+/// default.
+pub fn get_max_form_memory() -> u64 {
+    static MAX_FORM_MEMORY: OnceLock<u64> = OnceLock::new();
+    *MAX_FORM_MEMORY.get_or_init(|| {
+        env::var("MAX_FORM_MEMORY")
+            .unwrap_or("2097152".to_string())
+            .parse::<u64>()
+            .expect("MAX_FORM_MEMORY must be a number of bytes.")
+    })
+}
+
+/// Most bytes of a whole multipart form, files and text fields, `MAX_UPLOAD_SIZE`. By default
+/// enough for ten files of the largest size, as many as a post can have.
+pub fn get_max_upload_size() -> u64 {
+    static MAX_UPLOAD_SIZE: OnceLock<u64> = OnceLock::new();
+    *MAX_UPLOAD_SIZE.get_or_init(|| match env::var("MAX_UPLOAD_SIZE") {
+        Ok(size) => size
+            .parse::<u64>()
+            .expect("MAX_UPLOAD_SIZE must be a number of bytes."),
+        Err(_) => get_max_file_size() * 10 + get_max_form_memory(),
+    })
+}
+
+/// Reactions users can leave on posts, comma separated in `REACTION_KINDS`
+pub fn get_reaction_kinds() -> &'static Vec<String> {
+    static REACTION_KINDS: OnceLock<Vec<String>> = OnceLock::new();
+    REACTION_KINDS.get_or_init(|| {
+        env::var("REACTION_KINDS")
+            .unwrap_or("like,love,laugh,wow,sad,angry".to_string())
+            .split(',')
+            .map(|kind| kind.trim().to_lowercase())
+            .filter(|kind| !kind.is_empty())
+            .collect()
+    })
+}
+
+/// Number of hours a soft deleted post can still be restored by its owner
+pub fn get_post_restore_window_hours() -> i64 {
+    static RESTORE_WINDOW: OnceLock<i64> = OnceLock::new();
+    *RESTORE_WINDOW.get_or_init(|| {
+        env::var("POST_RESTORE_WINDOW_HOURS")
+            .unwrap_or("720".to_string())
+            .parse::<i64>()
+            .ok()
+            .filter(|hours| (0..=MAX_HOURS).contains(hours))
+            .expect("POST_RESTORE_WINDOW_HOURS must be a whole number of hours, at most 876000.")
+    })
+}
+
+/// Seconds between two runs of the deleted posts purge task
+pub fn get_purge_interval_secs() -> u64 {
+    static PURGE_INTERVAL: OnceLock<u64> = OnceLock::new();
+    *PURGE_INTERVAL.get_or_init(|| {
+        env::var("PURGE_INTERVAL_SECS")
+            .unwrap_or("3600".to_string())
+            .parse::<u64>()
+            .ok()
+            .filter(|interval| *interval > 0)
+            .expect("PURGE_INTERVAL_SECS must be a positive number of seconds.")
+    })
+}
+
+/// Longest time in seconds the publish scheduler sleeps before looking for due posts again
+pub fn get_publish_scheduler_interval_secs() -> u64 {
+    static PUBLISH_SCHEDULER_INTERVAL: OnceLock<u64> = OnceLock::new();
+    *PUBLISH_SCHEDULER_INTERVAL.get_or_init(|| {
+        env::var("PUBLISH_SCHEDULER_INTERVAL_SECS")
+            .unwrap_or("60".to_string())
+            .parse::<u64>()
+            .ok()
+            .filter(|interval| *interval > 0)
+            .expect("PUBLISH_SCHEDULER_INTERVAL_SECS must be a positive number of seconds.")
+    })
+}
+
+/// Longest side in pixels of each thumbnail made of uploaded images, comma separated in
+/// `THUMBNAIL_SIZES`
+pub fn get_thumbnail_sizes() -> &'static Vec<u32> {
+    static THUMBNAIL_SIZES: OnceLock<Vec<u32>> = OnceLock::new();
+    THUMBNAIL_SIZES.get_or_init(|| {
+        let mut sizes: Vec<u32> = env::var("THUMBNAIL_SIZES")
+            .unwrap_or("320,960".to_string())
+            .split(',')
+            .map(str::trim)
+            .filter(|size| !size.is_empty())
+            .map(|size| {
+                size.parse::<u32>()
+                    .expect("THUMBNAIL_SIZES must be a comma separated list of pixel sizes.")
+            })
+            .collect();
+        sizes.sort_unstable();
+        sizes.dedup();
+        sizes
+    })
+}
+
+/// Base URL the API is reachable at from clients, `PUBLIC_BASE_URL`. Used to build absolute
+/// links such as media URLs.
+pub fn get_public_base_url() -> &'static String {
+    static PUBLIC_BASE_URL: OnceLock<String> = OnceLock::new();
+    PUBLIC_BASE_URL.get_or_init(|| {
+        env::var("PUBLIC_BASE_URL")
+            .map(|url| url.trim_end_matches('/').to_string())
+            .unwrap_or_else(|_| format!("http://{}:{}", get_address(), get_port()))
+    })
+}
+
+/// Backend uploads are stored with, `STORAGE_BACKEND`. `local` or `s3`.
+pub fn get_storage_backend() -> &'static String {
+    static STORAGE_BACKEND: OnceLock<String> = OnceLock::new();
+    STORAGE_BACKEND.get_or_init(|| {
+        env::var("STORAGE_BACKEND")
+            .unwrap_or("local".to_string())
+            .trim()
+            .to_lowercase()
+    })
+}
+
+/// Directory the local storage backend keeps uploads in, `STORAGE_DIR`
+pub fn get_storage_dir() -> &'static String {
+    static STORAGE_DIR: OnceLock<String> = OnceLock::new();
+    STORAGE_DIR.get_or_init(|| env::var("STORAGE_DIR").unwrap_or("./public".to_string()))
+}
+
+/// Emails of the users allowed to use the admin endpoints, comma separated in `ADMIN_EMAILS`
+pub fn get_admin_emails() -> &'static Vec<String> {
+    static ADMIN_EMAILS: OnceLock<Vec<String>> = OnceLock::new();
+    ADMIN_EMAILS.get_or_init(|| {
+        env::var("ADMIN_EMAILS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|email| email.trim().to_lowercase())
+            .filter(|email| !email.is_empty())
+            .collect()
+    })
+}
+
+/// Seconds between two sweeps for orphaned media files
+pub fn get_media_sweep_interval_secs() -> u64 {
+    static MEDIA_SWEEP_INTERVAL: OnceLock<u64> = OnceLock::new();
+    *MEDIA_SWEEP_INTERVAL.get_or_init(|| {
+        env::var("MEDIA_SWEEP_INTERVAL_SECS")
+            .unwrap_or("86400".to_string())
+            .parse::<u64>()
+            .ok()
+            .filter(|interval| *interval > 0)
+            .expect("MEDIA_SWEEP_INTERVAL_SECS must be a positive number of seconds.")
+    })
+}
+
+/// Whether the periodic media sweep removes orphaned files, `MEDIA_SWEEP_DELETE`. Otherwise it
+/// only reports them.
+pub fn get_media_sweep_delete() -> bool {
+    static MEDIA_SWEEP_DELETE: OnceLock<bool> = OnceLock::new();
+    *MEDIA_SWEEP_DELETE.get_or_init(|| {
+        env::var("MEDIA_SWEEP_DELETE")
+            .unwrap_or("false".to_string())
+            .parse::<bool>()
+            .expect("MEDIA_SWEEP_DELETE must be true or false.")
+    })
+}
+
+/// Number of hours a stored file without references is kept, so uploads still being saved
+/// aren't taken for orphans
+pub fn get_media_orphan_grace_hours() -> i64 {
+    static MEDIA_ORPHAN_GRACE: OnceLock<i64> = OnceLock::new();
+    *MEDIA_ORPHAN_GRACE.get_or_init(|| {
+        env::var("MEDIA_ORPHAN_GRACE_HOURS")
+            .unwrap_or("24".to_string())
+            .parse::<i64>()
+            .ok()
+            .filter(|hours| (0..=MAX_HOURS).contains(hours))
+            .expect("MEDIA_ORPHAN_GRACE_HOURS must be a whole number of hours, at most 876000.")
+    })
+}
+
+/// Key signing the links to media of posts not everyone may see, `MEDIA_URL_SECRET`. Falls back
+/// to `SECRET`.
+pub fn get_media_url_secret() -> &'static String {
+    static MEDIA_URL_SECRET: OnceLock<String> = OnceLock::new();
+    MEDIA_URL_SECRET
+        .get_or_init(|| env::var("MEDIA_URL_SECRET").unwrap_or_else(|_| get_secret().clone()))
+}
+
+/// Number of seconds a signed media link stays valid at least. It stays the same for up to twice
+/// as long, so clients can cache the file.
+pub fn get_media_url_ttl_secs() -> i64 {
+    static MEDIA_URL_TTL: OnceLock<i64> = OnceLock::new();
+    *MEDIA_URL_TTL.get_or_init(|| {
+        env::var("MEDIA_URL_TTL_SECS")
+            .unwrap_or("3600".to_string())
+            .parse::<i64>()
+            .ok()
+            .filter(|ttl| *ttl > 0)
+            .expect("MEDIA_URL_TTL_SECS must be a positive number of seconds.")
+    })
+}
+
+/// Whether signed media links only work for the user they were made for, `MEDIA_URL_BIND_USER`.
+/// Fetching the file then needs the user's token, which `<img>` tags can't send.
+pub fn get_media_url_bind_user() -> bool {
+    static MEDIA_URL_BIND_USER: OnceLock<bool> = OnceLock::new();
+    *MEDIA_URL_BIND_USER.get_or_init(|| {
+        env::var("MEDIA_URL_BIND_USER")
+            .unwrap_or("false".to_string())
+            .parse::<bool>()
+            .expect("MEDIA_URL_BIND_USER must be true or false.")
+    })
+}
+
+/// Number of seconds in which repeated views of a post by the same viewer count once
+pub fn get_view_dedup_window_secs() -> u64 {
+    static VIEW_DEDUP_WINDOW: OnceLock<u64> = OnceLock::new();
+    *VIEW_DEDUP_WINDOW.get_or_init(|| {
+        env::var("VIEW_DEDUP_WINDOW_SECS")
+            .unwrap_or("1800".to_string())
+            .parse::<u64>()
+            .expect("VIEW_DEDUP_WINDOW_SECS must be a whole number of seconds.")
+    })
+}
+
+/// Seconds between two writes of the views counted in memory to the database
+pub fn get_view_flush_interval_secs() -> u64 {
+    static VIEW_FLUSH_INTERVAL: OnceLock<u64> = OnceLock::new();
+    *VIEW_FLUSH_INTERVAL.get_or_init(|| {
+        env::var("VIEW_FLUSH_INTERVAL_SECS")
+            .unwrap_or("60".to_string())
+            .parse::<u64>()
+            .ok()
+            .filter(|interval| *interval > 0)
+            .expect("VIEW_FLUSH_INTERVAL_SECS must be a positive number of seconds.")
+    })
+}
+
+/// Whether anonymous viewers are told apart by the `Forwarded` or `X-Forwarded-For` header,
+/// `TRUST_FORWARDED_FOR`. Only safe behind a proxy setting it, clients could fake views otherwise.
+pub fn get_trust_forwarded_for() -> bool {
+    static TRUST_FORWARDED_FOR: OnceLock<bool> = OnceLock::new();
+    *TRUST_FORWARDED_FOR.get_or_init(|| {
+        env::var("TRUST_FORWARDED_FOR")
+            .unwrap_or("false".to_string())
+            .parse::<bool>()
+            .expect("TRUST_FORWARDED_FOR must be true or false.")
+    })
+}
@@ -0,0 +1,163 @@
+//! Atom and RSS documents of post feeds
+
+use chrono::{DateTime, FixedOffset};
+use uuid::Uuid;
+
+/// A feed, written out as Atom or RSS
+#[derive(Debug)]
+pub(crate) struct Feed {
+    pub title: String,
+    /// URL the feed itself is served at
+    pub feed_url: String,
+    /// URL of the site the feed belongs to
+    pub site_url: String,
+    pub updated: DateTime<FixedOffset>,
+    /// Newest first
+    pub entries: Vec<FeedEntry>,
+}
+
+#[derive(Debug)]
+pub(crate) struct FeedEntry {
+    pub uuid: Uuid,
+    pub title: String,
+    pub link: String,
+    /// Rendered content, HTML
+    pub content_html: String,
+    pub author: Option<String>,
+    pub created_at: DateTime<FixedOffset>,
+    pub updated_at: DateTime<FixedOffset>,
+    pub enclosure: Option<Enclosure>,
+}
+
+/// Image of an entry
+#[derive(Debug)]
+pub(crate) struct Enclosure {
+    pub url: String,
+    pub media_type: &'static str,
+}
+
+impl Feed {
+    /// The feed as an Atom document
+    pub(crate) fn to_atom(&self) -> String {
+        let mut xml = format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+             <feed xmlns=\"http://www.w3.org/2005/Atom\">\n\
+             <id>{feed_url}</id>\n\
+             <title>{title}</title>\n\
+             <link rel=\"self\" type=\"application/atom+xml\" href=\"{feed_url}\"/>\n\
+             <link rel=\"alternate\" href=\"{site_url}\"/>\n\
+             <updated>{updated}</updated>\n",
+            feed_url = escape_xml(&self.feed_url),
+            title = escape_xml(&self.title),
+            site_url = escape_xml(&self.site_url),
+            updated = self.updated.to_rfc3339(),
+        );
+        for entry in &self.entries {
+            xml.push_str(&format!(
+                "<entry>\n\
+                 <id>urn:uuid:{}</id>\n\
+                 <title>{}</title>\n\
+                 <link rel=\"alternate\" href=\"{}\"/>\n\
+                 <published>{}</published>\n\
+                 <updated>{}</updated>\n",
+                entry.uuid,
+                escape_xml(&entry.title),
+                escape_xml(&entry.link),
+                entry.created_at.to_rfc3339(),
+                entry.updated_at.to_rfc3339(),
+            ));
+            if let Some(author) = &entry.author {
+                xml.push_str(&format!(
+                    "<author><name>{}</name></author>\n",
+                    escape_xml(author)
+                ));
+            }
+            if let Some(enclosure) = &entry.enclosure {
+                xml.push_str(&format!(
+                    "<link rel=\"enclosure\" type=\"{}\" href=\"{}\"/>\n",
+                    enclosure.media_type,
+                    escape_xml(&enclosure.url)
+                ));
+            }
+            xml.push_str(&format!(
+                "<content type=\"html\">{}</content>\n</entry>\n",
+                escape_xml(&entry.content_html)
+            ));
+        }
+        xml.push_str("</feed>\n");
+        xml
+    }
+
+    /// The feed as an RSS 2.0 document
+    pub(crate) fn to_rss(&self) -> String {
+        let mut xml = format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+             <rss version=\"2.0\" xmlns:atom=\"http://www.w3.org/2005/Atom\" \
+             xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n\
+             <channel>\n\
+             <title>{title}</title>\n\
+             <link>{site_url}</link>\n\
+             <description>{title}</description>\n\
+             <atom:link rel=\"self\" type=\"application/rss+xml\" href=\"{feed_url}\"/>\n\
+             <lastBuildDate>{updated}</lastBuildDate>\n",
+            title = escape_xml(&self.title),
+            site_url = escape_xml(&self.site_url),
+            feed_url = escape_xml(&self.feed_url),
+            updated = self.updated.to_rfc2822(),
+        );
+        for entry in &self.entries {
+            xml.push_str(&format!(
+                "<item>\n\
+                 <guid isPermaLink=\"false\">urn:uuid:{}</guid>\n\
+                 <title>{}</title>\n\
+                 <link>{}</link>\n\
+                 <pubDate>{}</pubDate>\n\
+                 <atom:updated>{}</atom:updated>\n",
+                entry.uuid,
+                escape_xml(&entry.title),
+                escape_xml(&entry.link),
+                entry.created_at.to_rfc2822(),
+                entry.updated_at.to_rfc3339(),
+            ));
+            // `author` has to be an email address, which isn't given out
+            if let Some(author) = &entry.author {
+                xml.push_str(&format!(
+                    "<dc:creator>{}</dc:creator>\n",
+                    escape_xml(author)
+                ));
+            }
+            if let Some(enclosure) = &entry.enclosure {
+                // The size of stored files isn't known, 0 is what readers expect then
+                xml.push_str(&format!(
+                    "<enclosure url=\"{}\" length=\"0\" type=\"{}\"/>\n",
+                    escape_xml(&enclosure.url),
+                    enclosure.media_type
+                ));
+            }
+            xml.push_str(&format!(
+                "<description>{}</description>\n</item>\n",
+                escape_xml(&entry.content_html)
+            ));
+        }
+        xml.push_str("</channel>\n</rss>\n");
+        xml
+    }
+}
+
+/// `text` escaped for XML text and attribute values
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for char in text.chars() {
+        match char {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Control characters aren't allowed in XML 1.0 at all
+            char if char.is_control() && !matches!(char, '\t' | '\n' | '\r') => (),
+            char => escaped.push(char),
+        }
+    }
+    escaped
+}
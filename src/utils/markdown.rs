@@ -0,0 +1,20 @@
+use std::collections::HashSet;
+
+use pulldown_cmark::{html, Options, Parser};
+
+/// Render CommonMark `text` into HTML that is safe to embed in a page. Only an allowlist of tags
+/// and attributes is kept, links are limited to http, https and mailto and raw HTML in the
+/// markdown can't inject scripts.
+pub fn render_markdown(text: &str) -> String {
+    // Task lists are left out, their checkboxes would not survive sanitizing
+    let options =
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_FOOTNOTES;
+    let mut unsafe_html = String::with_capacity(text.len() * 3 / 2);
+    html::push_html(&mut unsafe_html, Parser::new_ext(text, options));
+
+    ammonia::Builder::default()
+        .url_schemes(HashSet::from(["http", "https", "mailto"]))
+        .link_rel(Some("nofollow noopener noreferrer"))
+        .clean(&unsafe_html)
+        .to_string()
+}
@@ -0,0 +1,191 @@
+//! Reference counts of stored files. A file is stored once however often it is uploaded, and is
+//! only removed from storage once nothing refers to it anymore.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use entity::media;
+use sea_orm::{
+    sea_query::{Expr, OnConflict},
+    ColumnTrait, ConnectionTrait, DatabaseConnection, DatabaseTransaction, DbErr, EntityTrait,
+    QueryFilter, QueryOrder, QuerySelect, Set, TransactionTrait,
+};
+
+use super::{
+    api_response::ApiResponse,
+    uploads::{remove_images, ProcessedImage},
+};
+use crate::storage::Storage;
+
+/// Count one more reference to each of `file_names`. A name listed twice counts twice. Their rows
+/// stay locked until the transaction ends, so the files are to be put into storage after this:
+/// a concurrent removal then either went before or waits until they are referenced.
+pub(crate) async fn acquire_media<C: ConnectionTrait>(
+    db: &C,
+    file_names: impl IntoIterator<Item = &str>,
+) -> Result<(), DbErr> {
+    for (file_name, count) in count_names(file_names) {
+        media::Entity::insert(media::ActiveModel {
+            file_name: Set(file_name.to_string()),
+            ref_count: Set(count),
+            ..Default::default()
+        })
+        .on_conflict(
+            OnConflict::column(media::Column::FileName)
+                .value(
+                    media::Column::RefCount,
+                    Expr::col((media::Entity, media::Column::RefCount)).add(count),
+                )
+                .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Drop one reference to each of `file_names`. Files nothing refers to anymore are removed by
+/// [`remove_unreferenced`] before the transaction is committed.
+pub(crate) async fn release_media<C: ConnectionTrait>(
+    db: &C,
+    file_names: impl IntoIterator<Item = &str>,
+) -> Result<(), DbErr> {
+    for (file_name, count) in count_names(file_names) {
+        media::Entity::update_many()
+            .col_expr(
+                media::Column::RefCount,
+                Expr::col(media::Column::RefCount).sub(count),
+            )
+            .filter(media::Column::FileName.eq(file_name))
+            .exec(db)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Remove the files among `file_names` nothing refers to from `storage`, along with their rows.
+/// Meant to be called last in a transaction: the files are removed while their rows are locked,
+/// so an upload of the same file waits until they are gone rather than losing its file.
+pub(crate) async fn remove_unreferenced<C: ConnectionTrait>(
+    db: &C,
+    storage: &Arc<dyn Storage>,
+    file_names: impl IntoIterator<Item = &str>,
+) -> Result<(), DbErr> {
+    let unreferenced = claim_unreferenced(db, file_names).await?;
+    remove_claimed(db, storage, unreferenced).await
+}
+
+/// Lock the rows of the files among `file_names` nothing refers to until the transaction ends.
+/// Files without a row get one. Returns the names of the locked files.
+pub(crate) async fn claim_unreferenced<C: ConnectionTrait>(
+    db: &C,
+    file_names: impl IntoIterator<Item = &str>,
+) -> Result<Vec<String>, DbErr> {
+    let file_names = count_names(file_names).into_keys().collect::<Vec<&str>>();
+    if file_names.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Inserting the missing rows waits for an upload of the same file which isn't committed yet
+    media::Entity::insert_many(file_names.iter().map(|file_name| media::ActiveModel {
+        file_name: Set(file_name.to_string()),
+        ref_count: Set(0),
+        ..Default::default()
+    }))
+    .on_conflict(
+        OnConflict::column(media::Column::FileName)
+            .do_nothing()
+            .to_owned(),
+    )
+    .do_nothing()
+    .exec(db)
+    .await?;
+    media::Entity::find()
+        .select_only()
+        .column(media::Column::FileName)
+        .filter(media::Column::FileName.is_in(file_names))
+        .filter(media::Column::RefCount.lte(0))
+        .order_by_asc(media::Column::FileName)
+        .lock_exclusive()
+        .into_tuple()
+        .all(db)
+        .await
+}
+
+/// Delete the rows of `file_names` locked by [`claim_unreferenced`] and remove their files from
+/// `storage`
+pub(crate) async fn remove_claimed<C: ConnectionTrait>(
+    db: &C,
+    storage: &Arc<dyn Storage>,
+    file_names: Vec<String>,
+) -> Result<(), DbErr> {
+    if file_names.is_empty() {
+        return Ok(());
+    }
+
+    media::Entity::delete_many()
+        .filter(media::Column::FileName.is_in(file_names.iter().cloned()))
+        .exec(db)
+        .await?;
+    remove_images(storage.clone(), file_names).await;
+    Ok(())
+}
+
+/// Commit `txn` which attached `images` if `saved` succeeded. Otherwise, or if committing fails,
+/// it is rolled back and the files of `images` are discarded.
+pub(crate) async fn commit_upload<T>(
+    db: &DatabaseConnection,
+    storage: &Arc<dyn Storage>,
+    txn: DatabaseTransaction,
+    images: &[ProcessedImage],
+    saved: Result<T, ApiResponse>,
+) -> Result<T, ApiResponse> {
+    let committed = match saved {
+        Ok(saved) => txn
+            .commit()
+            .await
+            .map(|()| saved)
+            .map_err(|err| ApiResponse::new(500, err.to_string())),
+        Err(err) => {
+            // The rows of the files stay locked until then
+            if let Err(rollback_err) = txn.rollback().await {
+                log::warn!("Rolling back a failed upload failed: {}", rollback_err);
+            }
+            Err(err)
+        },
+    };
+    if committed.is_err() {
+        discard_images(db, storage, images).await;
+    }
+    committed
+}
+
+/// Remove the files of `images` from storage after their upload failed, leaving out the ones
+/// other posts refer to
+pub(crate) async fn discard_images(
+    db: &DatabaseConnection,
+    storage: &Arc<dyn Storage>,
+    images: &[ProcessedImage],
+) {
+    let discarded = async {
+        let txn = db.begin().await?;
+        remove_unreferenced(
+            &txn,
+            storage,
+            images.iter().flat_map(ProcessedImage::file_names),
+        )
+        .await?;
+        txn.commit().await
+    }
+    .await;
+    if let Err(err) = discarded {
+        log::warn!("Keeping the files of a failed upload: {}", err);
+    }
+}
+
+fn count_names<'a>(file_names: impl IntoIterator<Item = &'a str>) -> BTreeMap<&'a str, i32> {
+    let mut counts = BTreeMap::new();
+    for file_name in file_names {
+        *counts.entry(file_name).or_insert(0) += 1;
+    }
+    counts
+}
@@ -0,0 +1,225 @@
+//! Links to stored media. Files of posts not everyone may see are linked to with a signature,
+//! so they can't be fetched by guessing their names.
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use super::{
+    api_response::ApiResponse,
+    constants::{get_media_url_bind_user, get_media_url_secret, get_media_url_ttl_secs},
+};
+use crate::{
+    schemas::media_schemas::SignedMediaQuery,
+    storage::{media_route_url, Storage},
+};
+
+/// How the files of a post are linked to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MediaAccess {
+    /// Plain links, the post is public
+    Public,
+    /// Signed, expiring links through the media route. Only valid for the user, if any.
+    Signed(Option<i32>),
+}
+
+impl MediaAccess {
+    /// Access to the files of a post which is `public` or not, as shown to `viewer`
+    pub(crate) fn for_post(public: bool, viewer: Option<i32>) -> Self {
+        match public {
+            true => MediaAccess::Public,
+            false => MediaAccess::Signed(viewer.filter(|_| get_media_url_bind_user())),
+        }
+    }
+
+    /// Absolute URL of the stored file `file_name`
+    pub(crate) fn url(self, storage: &dyn Storage, file_name: &str) -> String {
+        match self {
+            MediaAccess::Public => storage.url(file_name),
+            MediaAccess::Signed(user) => signed_url(file_name, user),
+        }
+    }
+}
+
+/// URL of `file_name` on the media route, signed to be valid for `user` only if given
+fn signed_url(file_name: &str, user: Option<i32>) -> String {
+    let ttl = get_media_url_ttl_secs();
+    // Rounded up, so the link stays the same for a while and the file can be cached
+    let expires = (Utc::now().timestamp() / ttl + 2) * ttl;
+    let mut url = format!("{}?expires={}", media_route_url(file_name), expires);
+    if let Some(user) = user {
+        url.push_str(&format!("&user={}", user));
+    }
+    url.push_str(&format!(
+        "&signature={}",
+        hex::encode(
+            signature(get_media_url_secret(), file_name, expires, user)
+                .finalize()
+                .into_bytes()
+        )
+    ));
+    url
+}
+
+/// Check the signature of a link to `file_name`, requested by `viewer`. Returns the number of
+/// seconds the link stays valid, or 403 if it was tampered with, has expired or is for another
+/// user.
+pub(crate) fn verify_signed_url(
+    file_name: &str,
+    query: &SignedMediaQuery,
+    viewer: Option<i32>,
+) -> Result<i64, ApiResponse> {
+    verify_signature(
+        get_media_url_secret(),
+        Utc::now().timestamp(),
+        file_name,
+        query,
+        viewer,
+    )
+}
+
+/// [`verify_signed_url`] with the key `secret` at the Unix time `now`
+fn verify_signature(
+    secret: &str,
+    now: i64,
+    file_name: &str,
+    query: &SignedMediaQuery,
+    viewer: Option<i32>,
+) -> Result<i64, ApiResponse> {
+    let invalid = || ApiResponse::new(403, "Invalid media signature".to_string());
+    let expires = query.expires.ok_or_else(invalid)?;
+    let given = query
+        .signature
+        .as_deref()
+        .and_then(|signature| hex::decode(signature).ok())
+        .ok_or_else(invalid)?;
+    signature(secret, file_name, expires, query.user)
+        .verify_slice(&given)
+        .map_err(|_| invalid())?;
+
+    let remaining = expires - now;
+    if remaining <= 0 {
+        return Err(ApiResponse::new(403, "Media link has expired".to_string()));
+    }
+    if query.user.is_some() && query.user != viewer {
+        return Err(ApiResponse::new(
+            403,
+            "Media link was made for another user".to_string(),
+        ));
+    }
+
+    Ok(remaining)
+}
+
+fn signature(secret: &str, file_name: &str, expires: i64, user: Option<i32>) -> Hmac<Sha256> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any length");
+    // Separated by newlines, which no file name contains
+    mac.update(file_name.as_bytes());
+    mac.update(format!("\n{}\n", expires).as_bytes());
+    if let Some(user) = user {
+        mac.update(user.to_string().as_bytes());
+    }
+    mac
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "test secret";
+    const NOW: i64 = 1_700_000_000;
+    const INVALID: &str = "Invalid media signature";
+
+    fn signed_query(file_name: &str, expires: i64, user: Option<i32>) -> SignedMediaQuery {
+        SignedMediaQuery {
+            expires: Some(expires),
+            user,
+            signature: Some(hex::encode(
+                signature(SECRET, file_name, expires, user)
+                    .finalize()
+                    .into_bytes(),
+            )),
+        }
+    }
+
+    /// Why the link to `file_name` was refused to `viewer`
+    fn rejection(file_name: &str, query: &SignedMediaQuery, viewer: Option<i32>) -> String {
+        let response = verify_signature(SECRET, NOW, file_name, query, viewer)
+            .expect_err("the link should be refused");
+        assert_eq!(response.status_code, 403);
+        response.body
+    }
+
+    #[test]
+    fn accepts_valid_link() {
+        let query = signed_query("a.png", NOW + 60, None);
+        assert_eq!(
+            verify_signature(SECRET, NOW, "a.png", &query, Some(7)).ok(),
+            Some(60)
+        );
+        let query = signed_query("a.png", NOW + 60, Some(7));
+        assert_eq!(
+            verify_signature(SECRET, NOW, "a.png", &query, Some(7)).ok(),
+            Some(60)
+        );
+    }
+
+    #[test]
+    fn rejects_tampered_signature() {
+        let query = signed_query("a.png", NOW + 60, Some(7));
+        assert_eq!(rejection("b.png", &query, Some(7)), INVALID);
+        for tampered in [
+            SignedMediaQuery {
+                expires: Some(NOW + 3600),
+                ..signed_query("a.png", NOW + 60, Some(7))
+            },
+            SignedMediaQuery {
+                user: Some(8),
+                ..signed_query("a.png", NOW + 60, Some(7))
+            },
+            SignedMediaQuery {
+                user: None,
+                ..signed_query("a.png", NOW + 60, Some(7))
+            },
+            SignedMediaQuery {
+                signature: Some("00".repeat(32)),
+                ..signed_query("a.png", NOW + 60, Some(7))
+            },
+            SignedMediaQuery {
+                signature: Some("not hex".to_string()),
+                ..signed_query("a.png", NOW + 60, Some(7))
+            },
+            SignedMediaQuery {
+                signature: None,
+                ..signed_query("a.png", NOW + 60, Some(7))
+            },
+        ] {
+            assert_eq!(rejection("a.png", &tampered, tampered.user), INVALID);
+        }
+
+        let query = signed_query("a.png", NOW + 60, None);
+        let response = verify_signature("other secret", NOW, "a.png", &query, None)
+            .expect_err("the link should be refused");
+        assert_eq!(response.body, INVALID);
+    }
+
+    #[test]
+    fn rejects_expired_link() {
+        for expires in [NOW, NOW - 1] {
+            let query = signed_query("a.png", expires, None);
+            assert_eq!(rejection("a.png", &query, None), "Media link has expired");
+        }
+    }
+
+    #[test]
+    fn rejects_link_of_another_user() {
+        let query = signed_query("a.png", NOW + 60, Some(7));
+        for viewer in [Some(8), None] {
+            assert_eq!(
+                rejection("a.png", &query, viewer),
+                "Media link was made for another user"
+            );
+        }
+    }
+}
@@ -1,4 +1,15 @@
 pub mod api_response;
 pub mod app_state;
 pub mod constants;
+pub mod feeds;
 pub mod jwt;
+pub mod markdown;
+pub mod media;
+pub mod media_urls;
+pub mod pagination;
+pub mod post_visibility;
+pub mod search;
+pub mod slugs;
+pub mod tags;
+pub mod uploads;
+pub mod views;
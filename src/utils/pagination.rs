@@ -0,0 +1,145 @@
+//! Keyset (cursor) pagination over a timestamp and the row id
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, FixedOffset};
+use sea_orm::{
+    sea_query::{Expr, SimpleExpr},
+    ConnectionTrait, EntityTrait, Order, QueryFilter, QueryOrder, QuerySelect, Select,
+};
+use serde::{Deserialize, Serialize};
+
+use super::api_response::ApiResponse;
+use crate::schemas::pagination_schemas::{Page, PageQuery};
+
+pub(crate) const DEFAULT_PAGE_SIZE: u64 = 20;
+pub(crate) const MAX_PAGE_SIZE: u64 = 100;
+
+/// Position in a listing. Serialized and base64 encoded it becomes the opaque cursor.
+#[derive(Debug, Serialize, Deserialize)]
+struct Cursor {
+    /// Name of the sort order the cursor was created for
+    sort: String,
+    /// Sort key of the row the cursor points at
+    key: DateTime<FixedOffset>,
+    /// Id of the row the cursor points at
+    id: i32,
+    /// Whether the cursor asks for the rows before (`true`) or after (`false`) this one
+    backward: bool,
+}
+
+impl Cursor {
+    fn encode(&self) -> String {
+        // Serializing a struct of plain values can't fail
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    fn decode(cursor: &str) -> Result<Self, ApiResponse> {
+        URL_SAFE_NO_PAD
+            .decode(cursor)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .ok_or(ApiResponse::new(400, "Invalid cursor".to_string()))
+    }
+}
+
+/// The order of a listing: a timestamp expression with the row id as tie breaker
+#[derive(Debug, Clone)]
+pub(crate) struct SortKey {
+    /// Identifies the order in cursors, so a cursor can't be reused with another order
+    pub name: &'static str,
+    pub key: SimpleExpr,
+    pub id: SimpleExpr,
+    pub descending: bool,
+}
+
+/// Fetch one page of `select` ordered by `sort`. `key_of` has to return the values of the sort
+/// key and id for a model, as they are stored in the cursor.
+pub(crate) async fn paginate<E, C>(
+    db: &C,
+    select: Select<E>,
+    sort: &SortKey,
+    page: &PageQuery,
+    key_of: impl Fn(&E::Model) -> (DateTime<FixedOffset>, i32),
+) -> Result<Page<E::Model>, ApiResponse>
+where
+    E: EntityTrait,
+    C: ConnectionTrait,
+{
+    let limit = page
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let cursor = page.cursor.as_deref().map(Cursor::decode).transpose()?;
+    let sort_name = format!(
+        "{}:{}",
+        sort.name,
+        if sort.descending { "desc" } else { "asc" }
+    );
+    if cursor
+        .as_ref()
+        .is_some_and(|cursor| cursor.sort != sort_name)
+    {
+        return Err(ApiResponse::new(
+            400,
+            "Cursor does not belong to the requested sort order".to_string(),
+        ));
+    }
+    let backward = cursor.as_ref().is_some_and(|cursor| cursor.backward);
+
+    // Walking backward means reading the listing in reverse and flipping the result afterwards
+    let descending = sort.descending != backward;
+    let order = if descending { Order::Desc } else { Order::Asc };
+
+    let mut select = select
+        .order_by(sort.key.clone(), order.clone())
+        .order_by(sort.id.clone(), order)
+        .limit(limit + 1);
+
+    if let Some(cursor) = &cursor {
+        let row = Expr::tuple([sort.key.clone(), sort.id.clone()]);
+        let position = Expr::tuple([Expr::val(cursor.key).into(), Expr::val(cursor.id).into()]);
+        select = select.filter(if descending {
+            row.lt(position)
+        } else {
+            row.gt(position)
+        });
+    }
+
+    let mut data = select
+        .all(db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+
+    let has_more = data.len() as u64 > limit;
+    data.truncate(limit as usize);
+    if backward {
+        data.reverse();
+    }
+
+    let cursor_at = |model: Option<&E::Model>, backward: bool| {
+        model.map(|model| {
+            let (key, id) = key_of(model);
+            Cursor {
+                sort: sort_name.clone(),
+                key,
+                id,
+                backward,
+            }
+            .encode()
+        })
+    };
+
+    // Going forward there are earlier rows whenever a cursor was given, going backward there
+    // are later rows. The other direction depends on whether an extra row was fetched.
+    let (has_prev, has_next) = match cursor {
+        None => (false, has_more),
+        Some(_) if backward => (has_more, true),
+        Some(_) => (true, has_more),
+    };
+
+    Ok(Page {
+        next_cursor: has_next.then(|| cursor_at(data.last(), false)).flatten(),
+        prev_cursor: has_prev.then(|| cursor_at(data.first(), true)).flatten(),
+        data,
+    })
+}
@@ -0,0 +1,205 @@
+//! Rules deciding which posts can be shown to whom
+
+use entity::{
+    attachment_thumbnail, follow, post, post_attachment,
+    sea_orm_active_enums::{PostStatus, PostVisibility},
+};
+use sea_orm::{
+    sea_query::{Expr, Query, SelectStatement},
+    ColumnTrait, Condition, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait,
+    PaginatorTrait, QueryFilter,
+};
+use uuid::Uuid;
+
+use super::api_response::ApiResponse;
+
+/// Condition matching the posts every visitor may see in listings, search and feeds
+pub(crate) fn publicly_listed() -> Condition {
+    published().add(post::Column::Visibility.eq(PostVisibility::Public))
+}
+
+/// Condition matching the posts every visitor may open by their uuid, listed or not
+pub(crate) fn publicly_viewable() -> Condition {
+    published()
+        .add(post::Column::Visibility.is_in([PostVisibility::Public, PostVisibility::Unlisted]))
+}
+
+/// Whether every visitor may open `post`, the same as [`publicly_viewable`]
+pub(crate) fn is_publicly_viewable(post: &post::Model) -> bool {
+    post.deleted_at.is_none()
+        && post.status == PostStatus::Published
+        && matches!(
+            post.visibility,
+            PostVisibility::Public | PostVisibility::Unlisted
+        )
+}
+
+/// Condition matching the posts listed to `viewer`, the id of the authenticated user if any.
+/// Besides public posts these are the followers only posts of users they follow and their own
+/// posts. Unlisted posts are left out for everyone.
+pub(crate) fn listed_for(viewer: Option<i32>) -> Condition {
+    let Some(viewer) = viewer else {
+        return publicly_listed();
+    };
+    published().add(
+        Condition::any()
+            .add(post::Column::Visibility.eq(PostVisibility::Public))
+            .add(
+                Condition::all()
+                    .add(
+                        post::Column::Visibility
+                            .is_in([PostVisibility::Followers, PostVisibility::Private]),
+                    )
+                    .add(post::Column::UserId.eq(viewer)),
+            )
+            .add(
+                Condition::all()
+                    .add(post::Column::Visibility.eq(PostVisibility::Followers))
+                    .add(post::Column::UserId.in_subquery(followees_of(viewer))),
+            ),
+    )
+}
+
+/// Condition matching the posts `viewer` may open by their uuid, the same as
+/// [`check_viewable`] except for deleted posts, which are left out
+pub(crate) fn viewable_by(viewer: i32) -> Condition {
+    Condition::any()
+        .add(
+            Condition::all()
+                .add(post::Column::UserId.eq(viewer))
+                .add(post::Column::DeletedAt.is_null()),
+        )
+        .add(
+            published().add(
+                Condition::any()
+                    .add(
+                        post::Column::Visibility
+                            .is_in([PostVisibility::Public, PostVisibility::Unlisted]),
+                    )
+                    .add(
+                        Condition::all()
+                            .add(post::Column::Visibility.eq(PostVisibility::Followers))
+                            .add(post::Column::UserId.in_subquery(followees_of(viewer))),
+                    ),
+            ),
+        )
+}
+
+/// Ids of the users `follower` follows
+pub(crate) fn followees_of(follower: i32) -> SelectStatement {
+    Query::select()
+        .column(follow::Column::FolloweeId)
+        .from(follow::Entity)
+        .and_where(follow::Column::FollowerId.eq(follower))
+        .to_owned()
+}
+
+/// Whether `follower` follows `followee`
+pub(crate) async fn follows<C: ConnectionTrait>(
+    db: &C,
+    follower: i32,
+    followee: i32,
+) -> Result<bool, DbErr> {
+    let count = follow::Entity::find_by_id((follower, followee))
+        .count(db)
+        .await?;
+    Ok(count > 0)
+}
+
+fn published() -> Condition {
+    Condition::all()
+        .add(post::Column::DeletedAt.is_null())
+        .add(post::Column::Status.eq(PostStatus::Published))
+}
+
+/// Whether the stored file `file_name` belongs to a post every visitor may open, so it can be
+/// served without a signed link
+pub(crate) async fn is_public_media(
+    db: &DatabaseConnection,
+    file_name: &str,
+) -> Result<bool, DbErr> {
+    let attached = Query::select()
+        .column(post_attachment::Column::PostId)
+        .from(post_attachment::Entity)
+        .and_where(post_attachment::Column::FileName.eq(file_name))
+        .to_owned();
+    let thumbnailed = Query::select()
+        .column((post_attachment::Entity, post_attachment::Column::PostId))
+        .from(attachment_thumbnail::Entity)
+        .inner_join(
+            post_attachment::Entity,
+            Expr::col((
+                attachment_thumbnail::Entity,
+                attachment_thumbnail::Column::AttachmentId,
+            ))
+            .equals((post_attachment::Entity, post_attachment::Column::Id)),
+        )
+        .and_where(
+            Expr::col((
+                attachment_thumbnail::Entity,
+                attachment_thumbnail::Column::FileName,
+            ))
+            .eq(file_name),
+        )
+        .to_owned();
+    let count = post::Entity::find()
+        .filter(publicly_viewable())
+        .filter(
+            Condition::any()
+                .add(post::Column::Id.in_subquery(attached))
+                .add(post::Column::Id.in_subquery(thumbnailed)),
+        )
+        .count(db)
+        .await?;
+    Ok(count > 0)
+}
+
+/// Find a post which can be opened by its uuid. `viewer` is the id of the authenticated user,
+/// if any. Unpublished and private posts are only found by their author, followers only posts
+/// also by the author's followers. Unknown posts are reported with 404, deleted ones with 410.
+pub(crate) async fn find_viewable_post(
+    db: &DatabaseConnection,
+    post_uuid: Uuid,
+    viewer: Option<i32>,
+) -> Result<post::Model, ApiResponse> {
+    let post = post::Entity::find()
+        .filter(post::Column::Uuid.eq(post_uuid))
+        .one(db)
+        .await
+        .map_err(|err| ApiResponse::new(500, err.to_string()))?
+        .ok_or(ApiResponse::new(404, "No post found".to_string()))?;
+
+    check_viewable(db, post, viewer).await
+}
+
+/// Make sure `viewer` may open `post`, with the same rules as [`find_viewable_post`].
+pub(crate) async fn check_viewable(
+    db: &DatabaseConnection,
+    post: post::Model,
+    viewer: Option<i32>,
+) -> Result<post::Model, ApiResponse> {
+    if viewer != Some(post.user_id) {
+        // Posts the viewer may not see are reported as unknown, so their existence isn't told
+        let not_found = || ApiResponse::new(404, "No post found".to_string());
+        if post.status != PostStatus::Published {
+            return Err(not_found());
+        }
+        match (post.visibility, viewer) {
+            (PostVisibility::Public | PostVisibility::Unlisted, _) => (),
+            (PostVisibility::Followers, Some(viewer)) => {
+                let follower = follows(db, viewer, post.user_id)
+                    .await
+                    .map_err(|err| ApiResponse::new(500, err.to_string()))?;
+                if !follower {
+                    return Err(not_found());
+                }
+            },
+            (PostVisibility::Followers | PostVisibility::Private, _) => return Err(not_found()),
+        }
+    }
+    if post.deleted_at.is_some() {
+        return Err(ApiResponse::new(410, "Post has been deleted".to_string()));
+    }
+
+    Ok(post)
+}
@@ -0,0 +1,57 @@
+//! Helpers for the PostgreSQL full text search over posts
+
+/// Marks the start of a match in `ts_headline` output, replaced by `<mark>` once escaped
+pub(crate) const MATCH_START: char = '\u{2}';
+/// Marks the end of a match in `ts_headline` output, replaced by `</mark>` once escaped
+pub(crate) const MATCH_END: char = '\u{3}';
+
+/// Translate a user search into `to_tsquery` syntax. Words are combined with AND,
+/// `"quoted words"` have to appear as a phrase and a trailing `*` makes a word a prefix.
+/// Everything except letters and digits is dropped, so the result is always a valid query.
+/// Returns `None` if nothing searchable is left.
+pub(crate) fn to_tsquery(search: &str) -> Option<String> {
+    let mut terms = Vec::new();
+
+    for (index, part) in search.split('"').enumerate() {
+        // Odd parts are between quotes
+        let in_phrase = index % 2 == 1;
+        let words: Vec<String> = part.split_whitespace().filter_map(to_lexeme).collect();
+
+        if in_phrase && words.len() > 1 {
+            terms.push(format!("({})", words.join(" <-> ")));
+        } else {
+            terms.extend(words);
+        }
+    }
+
+    (!terms.is_empty()).then(|| terms.join(" & "))
+}
+
+fn to_lexeme(word: &str) -> Option<String> {
+    let prefix = word.ends_with('*');
+    let cleaned: String = word.chars().filter(|c| c.is_alphanumeric()).collect();
+
+    match (cleaned.is_empty(), prefix) {
+        (true, _) => None,
+        (false, true) => Some(format!("{}:*", cleaned)),
+        (false, false) => Some(cleaned),
+    }
+}
+
+/// HTML escape `ts_headline` output and turn the match markers into `<mark>` tags.
+pub(crate) fn highlight(headline: &str) -> String {
+    let mut html = String::with_capacity(headline.len());
+    for c in headline.chars() {
+        match c {
+            MATCH_START => html.push_str("<mark>"),
+            MATCH_END => html.push_str("</mark>"),
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#39;"),
+            _ => html.push(c),
+        }
+    }
+    html
+}
@@ -0,0 +1,19 @@
+//! URL safe names of posts
+
+const MAX_SLUG_LENGTH: usize = 80;
+
+/// Turn `title` into a slug: transliterated to lowercase ASCII letters and digits separated by
+/// single dashes. Titles without any of those become `post`.
+pub fn slugify(title: &str) -> String {
+    let slug = slug::slugify(title);
+    let slug = slug
+        .chars()
+        .take(MAX_SLUG_LENGTH)
+        .collect::<String>()
+        .trim_end_matches('-')
+        .to_string();
+    match slug.is_empty() {
+        true => "post".to_string(),
+        false => slug,
+    }
+}
@@ -0,0 +1,174 @@
+//! Normalization of tag names and extraction of `#hashtags` from post text
+
+use std::collections::BTreeSet;
+
+use unicode_normalization::UnicodeNormalization;
+
+pub(crate) const MAX_TAG_LENGTH: usize = 64;
+
+/// Bring a tag into its canonical form: NFKC normalized, lowercase and without a leading `#`.
+/// Only letters, digits and `_` are allowed. Returns `None` for anything that isn't a valid tag.
+pub(crate) fn normalize_tag(tag: &str) -> Option<String> {
+    // `#1` is a number rather than a tag
+    normalize_tag_prefix(tag).filter(|tag| !tag.chars().all(|c| c.is_numeric() || c == '_'))
+}
+
+/// Bring the start of a tag into the canonical form of [`normalize_tag`]. Unlike whole tags it
+/// may be all digits, as in `2024` for `2024election`.
+pub(crate) fn normalize_tag_prefix(prefix: &str) -> Option<String> {
+    let prefix: String = prefix
+        .trim()
+        .trim_start_matches('#')
+        .nfkc()
+        .flat_map(char::to_lowercase)
+        .collect();
+
+    let valid = !prefix.is_empty()
+        && prefix.chars().count() <= MAX_TAG_LENGTH
+        && prefix.chars().all(|c| c.is_alphanumeric() || c == '_');
+
+    valid.then_some(prefix)
+}
+
+/// Parse tags given explicitly, separated by commas or whitespace. Invalid tags are reported
+/// back as the error.
+pub(crate) fn parse_tags<'a>(
+    inputs: impl IntoIterator<Item = &'a str>,
+) -> Result<BTreeSet<String>, String> {
+    inputs
+        .into_iter()
+        .flat_map(|input| input.split(|c: char| c == ',' || c.is_whitespace()))
+        .filter(|tag| !tag.is_empty())
+        .map(|tag| normalize_tag(tag).ok_or(tag.to_string()))
+        .collect()
+}
+
+/// Find the `#hashtags` in `text`. A hashtag starts with `#` at the beginning of a word and runs
+/// as long as letters, digits or `_` follow, so Markdown headings (`# Title`) and anchors inside
+/// words (`a#b`) are not picked up.
+pub(crate) fn extract_hashtags(text: &str) -> BTreeSet<String> {
+    let mut tags = BTreeSet::new();
+    let mut previous: Option<char> = None;
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        let at_word_start = previous.is_none_or(|p| !(p.is_alphanumeric() || p == '_' || p == '#'));
+        previous = Some(c);
+        if c != '#' || !at_word_start {
+            continue;
+        }
+
+        let mut end = start + c.len_utf8();
+        while let Some(&(index, next)) = chars.peek() {
+            if !(next.is_alphanumeric() || next == '_') {
+                break;
+            }
+            end = index + next.len_utf8();
+            previous = Some(next);
+            chars.next();
+        }
+
+        if let Some(tag) = normalize_tag(&text[start..end]) {
+            tags.insert(tag);
+        }
+    }
+
+    tags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(tags: &[&str]) -> BTreeSet<String> {
+        tags.iter().map(|tag| tag.to_string()).collect()
+    }
+
+    #[test]
+    fn normalizes_tags() {
+        assert_eq!(normalize_tag("Rust").as_deref(), Some("rust"));
+        assert_eq!(normalize_tag("  #Rust_Lang ").as_deref(), Some("rust_lang"));
+        assert_eq!(normalize_tag("##rust").as_deref(), Some("rust"));
+        assert_eq!(
+            normalize_tag("2024election").as_deref(),
+            Some("2024election")
+        );
+        assert_eq!(normalize_tag("Straße").as_deref(), Some("straße"));
+        // Full width letters are the same tag
+        assert_eq!(normalize_tag("ＲＵＳＴ").as_deref(), Some("rust"));
+        assert_eq!(
+            normalize_tag("Ä").as_deref(),
+            normalize_tag("A\u{308}").as_deref()
+        );
+        let longest = "a".repeat(MAX_TAG_LENGTH);
+        assert_eq!(normalize_tag(&longest), Some(longest));
+    }
+
+    #[test]
+    fn rejects_invalid_tags() {
+        for tag in [
+            "",
+            "#",
+            "  ",
+            "2024",
+            "#1",
+            "_",
+            "1_2",
+            "rust-lang",
+            "rust lang",
+            "c++",
+            "a#b",
+            "#rust!",
+        ] {
+            assert_eq!(normalize_tag(tag), None, "{:?}", tag);
+        }
+        assert_eq!(normalize_tag(&"a".repeat(MAX_TAG_LENGTH + 1)), None);
+    }
+
+    #[test]
+    fn normalizes_prefixes_of_digits() {
+        assert_eq!(normalize_tag_prefix("2024").as_deref(), Some("2024"));
+        assert_eq!(normalize_tag_prefix("#Rus").as_deref(), Some("rus"));
+        assert_eq!(normalize_tag_prefix("_").as_deref(), Some("_"));
+        for prefix in ["", "#", "rust-", "a b"] {
+            assert_eq!(normalize_tag_prefix(prefix), None, "{:?}", prefix);
+        }
+    }
+
+    #[test]
+    fn parses_tag_lists() {
+        assert_eq!(
+            parse_tags(["Rust, web  #Actix", "rust,,sql\tdb"]),
+            Ok(tags(&["actix", "db", "rust", "sql", "web"]))
+        );
+        assert_eq!(parse_tags([" , "]), Ok(BTreeSet::new()));
+        assert_eq!(parse_tags(Vec::<&str>::new()), Ok(BTreeSet::new()));
+        assert_eq!(parse_tags(["rust, 2024, web"]), Err("2024".to_string()));
+        assert_eq!(parse_tags(["rust", "c++"]), Err("c++".to_string()));
+    }
+
+    #[test]
+    fn extracts_hashtags() {
+        assert_eq!(
+            extract_hashtags("Learning #Rust and #actix_web. #rust again (#Sql)"),
+            tags(&["actix_web", "rust", "sql"])
+        );
+        assert_eq!(extract_hashtags("#start and end#"), tags(&["start"]));
+        assert_eq!(
+            extract_hashtags("#Straße #ＲＵＳＴ"),
+            tags(&["rust", "straße"])
+        );
+    }
+
+    #[test]
+    fn skips_what_isnt_a_hashtag() {
+        // Headings, anchors inside words, numbers and doubled signs
+        assert_eq!(
+            extract_hashtags("# Title\n## Section\nsee a#b and x.org#frag"),
+            BTreeSet::new()
+        );
+        assert_eq!(extract_hashtags("Issue #42 and #1_2"), BTreeSet::new());
+        assert_eq!(extract_hashtags("##rust #_"), BTreeSet::new());
+        assert_eq!(extract_hashtags("#rust-lang"), tags(&["rust"]));
+    }
+}
@@ -0,0 +1,375 @@
+//! Validation and storage of uploaded images
+
+use std::{future::Future, io::Cursor, path::Path, pin::Pin, sync::Arc};
+
+use actix_multipart::{
+    form::{tempfile::TempFile, FieldReader, Limits},
+    Field, MultipartError,
+};
+use actix_web::{web, HttpRequest};
+use image::{codecs::jpeg::JpegEncoder, DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+use sha2::{Digest, Sha256};
+
+use super::{
+    api_response::ApiResponse,
+    constants::{get_max_file_size, get_thumbnail_sizes},
+};
+use crate::storage::Storage;
+
+const JPEG_QUALITY: u8 = 85;
+
+/// Media types accepted for uploaded images, with their formats and file extensions
+const ALLOWED_IMAGE_TYPES: [(&str, ImageFormat, &str); 4] = [
+    ("image/png", ImageFormat::Png, "png"),
+    ("image/jpeg", ImageFormat::Jpeg, "jpg"),
+    ("image/gif", ImageFormat::Gif, "gif"),
+    ("image/webp", ImageFormat::WebP, "webp"),
+];
+
+/// A file of a multipart form, written to a temporary file which is removed when dropped. Reading
+/// it fails as soon as it grows past `MAX_FILE_SIZE`, rather than once it filled the disk.
+#[derive(Debug)]
+pub(crate) struct UploadedFile(pub TempFile);
+
+impl<'t> FieldReader<'t> for UploadedFile {
+    type Future = Pin<Box<dyn Future<Output = Result<Self, MultipartError>> + 't>>;
+
+    fn read_field(req: &'t HttpRequest, field: Field, limits: &'t mut Limits) -> Self::Future {
+        Box::pin(async move {
+            // Any limit of the field is shared by all files sent under its name
+            let shared = limits.field_limit_remaining;
+            let max_file_size = get_max_file_size() as usize;
+            limits.field_limit_remaining =
+                Some(shared.map_or(max_file_size, |shared| shared.min(max_file_size)));
+            let file = TempFile::read_field(req, field, limits).await?;
+            limits.field_limit_remaining = shared.map(|shared| shared - file.size);
+            Ok(UploadedFile(file))
+        })
+    }
+}
+
+/// An uploaded image, validated and encoded again, ready to be put into storage
+#[derive(Debug)]
+pub(crate) struct ProcessedImage {
+    /// Named by the SHA-256 hash of its content, so equal images share a single file
+    pub file_name: String,
+    pub media_type: &'static str,
+    pub width: i32,
+    pub height: i32,
+    pub bytes: Vec<u8>,
+    /// Downscaled copies, smallest first
+    pub thumbnails: Vec<ProcessedThumbnail>,
+}
+
+#[derive(Debug)]
+pub(crate) struct ProcessedThumbnail {
+    /// The configured size this thumbnail was made for
+    pub size: i32,
+    pub file_name: String,
+    pub width: i32,
+    pub height: i32,
+    pub bytes: Vec<u8>,
+}
+
+impl ProcessedImage {
+    /// Names of the files of the image and its thumbnails
+    pub(crate) fn file_names(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.file_name.as_str()).chain(
+            self.thumbnails
+                .iter()
+                .map(|thumbnail| thumbnail.file_name.as_str()),
+        )
+    }
+}
+
+/// Validate an uploaded image and make its thumbnails. The type of the image is told by its
+/// content, the name the client gave it doesn't matter. The image is decoded, turned upright and
+/// encoded again, which drops all metadata such as EXIF. Animated images keep their first frame
+/// only.
+pub(crate) fn process_image(in_file: &TempFile) -> Result<ProcessedImage, ApiResponse> {
+    let max_file_size = get_max_file_size() as usize;
+
+    match in_file.size {
+        0 => return Err(ApiResponse::new(400, "Invalid File Type".to_string())),
+        length if length > max_file_size => {
+            return Err(ApiResponse::new(413, "File too big".to_string()));
+        },
+        _ => (),
+    }
+
+    let tmp_file_path = in_file.file.path();
+
+    let bytes = std::fs::read(tmp_file_path);
+    std::fs::remove_file(tmp_file_path).unwrap_or_default();
+    let bytes = bytes
+        .map_err(|err| ApiResponse::new(500, format!("Internal server error. Details: {}", err)))?;
+
+    let (media_type, format, extension) = sniff_image_type(&bytes).ok_or(ApiResponse::new(
+        415,
+        format!(
+            "Unsupported media type, images have to be one of {}",
+            ALLOWED_IMAGE_TYPES
+                .map(|(media_type, _, _)| media_type)
+                .join(", ")
+        ),
+    ))?;
+    // Clients sending a generic type don't know better, any other type has to match the content
+    if let Some(declared) = in_file.content_type.as_ref() {
+        let declared = match declared.essence_str() {
+            "image/jpg" | "image/pjpeg" => "image/jpeg",
+            declared => declared,
+        };
+        if declared != media_type && declared != "application/octet-stream" {
+            return Err(ApiResponse::new(
+                415,
+                format!(
+                    "Declared content type {} does not match the uploaded {} image",
+                    declared, media_type
+                ),
+            ));
+        }
+    }
+
+    let image = decode_image(&bytes, format).ok_or(ApiResponse::new(
+        400,
+        "Bad Request, Invalid Image".to_string(),
+    ))?;
+
+    let bytes = encode_image(&image, format)?;
+    let mut processed = ProcessedImage {
+        file_name: content_file_name(&bytes, extension),
+        media_type,
+        width: image.width() as i32,
+        height: image.height() as i32,
+        bytes,
+        thumbnails: Vec::new(),
+    };
+    let longest_side = image.width().max(image.height());
+    for &size in get_thumbnail_sizes() {
+        if size == 0 || size >= longest_side {
+            continue;
+        }
+        let thumbnail = image.thumbnail(size, size);
+        let bytes = encode_image(&thumbnail, format)?;
+        processed.thumbnails.push(ProcessedThumbnail {
+            size: size as i32,
+            file_name: content_file_name(&bytes, extension),
+            width: thumbnail.width() as i32,
+            height: thumbnail.height() as i32,
+            bytes,
+        });
+    }
+
+    Ok(processed)
+}
+
+/// Process several uploaded images, failing if one of them is rejected
+pub(crate) fn process_images(
+    in_files: &[UploadedFile],
+) -> Result<Vec<ProcessedImage>, ApiResponse> {
+    in_files
+        .iter()
+        .map(|in_file| process_image(&in_file.0))
+        .collect()
+}
+
+/// Put the files of `images` into `storage`. Files already there are replaced by the same
+/// content. Files put before a failure are left for the caller to clean up.
+pub(crate) fn put_images(
+    storage: &dyn Storage,
+    images: &[ProcessedImage],
+) -> Result<(), ApiResponse> {
+    for image in images {
+        let files = std::iter::once((&image.file_name, &image.bytes)).chain(
+            image
+                .thumbnails
+                .iter()
+                .map(|thumbnail| (&thumbnail.file_name, &thumbnail.bytes)),
+        );
+        for (file_name, bytes) in files {
+            storage
+                .put(file_name, bytes.clone(), image.media_type)
+                .map_err(|err| {
+                    ApiResponse::new(500, format!("Internal server error. Details: {}", err))
+                })?;
+        }
+    }
+    Ok(())
+}
+
+/// Remove a previously stored image. Failures are only logged, as the image is no longer used.
+pub(crate) fn remove_image(storage: &dyn Storage, file_name: &str) {
+    if let Err(err) = storage.delete(file_name) {
+        log::warn!("Removing {} failed: {}", file_name, err);
+    }
+}
+
+/// Remove previously stored images on a blocking thread, as [`remove_image`] does
+pub(crate) async fn remove_images(storage: Arc<dyn Storage>, file_names: Vec<String>) {
+    if file_names.is_empty() {
+        return;
+    }
+    let removed = web::block(move || {
+        for file_name in &file_names {
+            remove_image(storage.as_ref(), file_name);
+        }
+    })
+    .await;
+    if let Err(err) = removed {
+        log::warn!("Removing images failed: {}", err);
+    }
+}
+
+/// Media type of the stored image `file_name`. `None` for names which can't belong to a stored
+/// image, such as ones reaching out of the storage.
+pub(crate) fn media_type(file_name: &str) -> Option<&'static str> {
+    if file_name.starts_with('.') || sanitize_filename::sanitize(file_name) != file_name {
+        return None;
+    }
+    let extension = match Path::new(file_name).extension()?.to_str()? {
+        // Name used for JPEG images before the extension followed the content
+        "jpeg" => "jpg",
+        extension => extension,
+    };
+    let (media_type, _, _) = ALLOWED_IMAGE_TYPES
+        .into_iter()
+        .find(|(_, _, allowed)| *allowed == extension)?;
+    Some(media_type)
+}
+
+/// Whether the content of `file_name` never changes. That holds for files named by their
+/// content hash and ones made unique by a random part. Older uploads only carry a timestamp and
+/// may have been overwritten by a namesake.
+pub(crate) fn is_immutable_name(file_name: &str) -> bool {
+    if let Some((hash, _)) = file_name.split_once('.') {
+        if hash.len() == 64
+            && hash
+                .bytes()
+                .all(|byte| matches!(byte, b'0'..=b'9' | b'a'..=b'f'))
+        {
+            return true;
+        }
+    }
+    let mut parts = file_name.splitn(3, '-');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(time_stamp), Some(unique), Some(_)) => {
+            !time_stamp.is_empty()
+                && time_stamp.bytes().all(|byte| byte.is_ascii_digit())
+                && unique.len() == 8
+                && unique
+                    .bytes()
+                    .all(|byte| matches!(byte, b'0'..=b'9' | b'a'..=b'f'))
+        },
+        _ => false,
+    }
+}
+
+/// Tell the type of an image by the magic bytes it starts with. `None` for anything but the
+/// allowed image types.
+fn sniff_image_type(bytes: &[u8]) -> Option<(&'static str, ImageFormat, &'static str)> {
+    let media_type = match bytes {
+        [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, ..] => "image/png",
+        [0xFF, 0xD8, 0xFF, ..] => "image/jpeg",
+        [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => "image/gif",
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => "image/webp",
+        _ => return None,
+    };
+    ALLOWED_IMAGE_TYPES
+        .into_iter()
+        .find(|(allowed, _, _)| *allowed == media_type)
+}
+
+/// Name of a file with the content `bytes`
+fn content_file_name(bytes: &[u8], extension: &str) -> String {
+    format!("{}.{}", hex::encode(Sha256::digest(bytes)), extension)
+}
+
+/// Decode `bytes` as `format` and turn the image as its EXIF orientation says. `None` if it
+/// can't be decoded.
+fn decode_image(bytes: &[u8], format: ImageFormat) -> Option<DynamicImage> {
+    let mut decoder = ImageReader::with_format(Cursor::new(bytes), format)
+        .into_decoder()
+        .ok()?;
+    let orientation = decoder.orientation().ok()?;
+    let mut image = DynamicImage::from_decoder(decoder).ok()?;
+    image.apply_orientation(orientation);
+    Some(image)
+}
+
+/// Encode `image` as `format`
+fn encode_image(image: &DynamicImage, format: ImageFormat) -> Result<Vec<u8>, ApiResponse> {
+    let mut bytes = Cursor::new(Vec::new());
+    let encoded = match format {
+        // JPEG has no alpha channel
+        ImageFormat::Jpeg => {
+            JpegEncoder::new_with_quality(&mut bytes, JPEG_QUALITY).encode_image(&image.to_rgb8())
+        },
+        // The WebP and GIF encoders only take 8 bit RGBA
+        ImageFormat::WebP | ImageFormat::Gif => image.to_rgba8().write_to(&mut bytes, format),
+        _ => image.write_to(&mut bytes, format),
+    };
+    encoded
+        .map_err(|err| ApiResponse::new(500, format!("Internal server error. Details: {}", err)))?;
+    Ok(bytes.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn media_type_follows_extension() {
+        assert_eq!(media_type("a.png"), Some("image/png"));
+        assert_eq!(media_type("a.jpg"), Some("image/jpeg"));
+        assert_eq!(media_type("a.jpeg"), Some("image/jpeg"));
+        assert_eq!(
+            media_type("1700000000-0123abcd-w320-a.webp"),
+            Some("image/webp")
+        );
+        assert_eq!(media_type("a.gif"), Some("image/gif"));
+        assert_eq!(media_type("a.svg"), None);
+        assert_eq!(media_type("a"), None);
+    }
+
+    #[test]
+    fn media_type_rejects_names_out_of_storage() {
+        for file_name in [
+            "..",
+            "../a.png",
+            "..png",
+            ".png",
+            ".hidden.png",
+            "a/../b.png",
+            "a/b.png",
+            "a\\b.png",
+            "/etc/a.png",
+            "",
+        ] {
+            assert_eq!(media_type(file_name), None, "{:?}", file_name);
+        }
+    }
+
+    #[test]
+    fn sniffs_allowed_image_types() {
+        let sniffed = |bytes: &[u8]| sniff_image_type(bytes).map(|(media_type, _, _)| media_type);
+        assert_eq!(sniffed(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), Some("image/png"));
+        assert_eq!(sniffed(b"\xff\xd8\xff\xe0\0\x10JFIF"), Some("image/jpeg"));
+        assert_eq!(sniffed(b"GIF87a"), Some("image/gif"));
+        assert_eq!(sniffed(b"GIF89a"), Some("image/gif"));
+        assert_eq!(sniffed(b"RIFF\0\0\0\0WEBPVP8 "), Some("image/webp"));
+    }
+
+    #[test]
+    fn sniffing_rejects_other_content() {
+        for bytes in [
+            &b""[..],
+            b"\x89PNG",
+            b"GIF88a",
+            b"RIFF\0\0\0\0WAVEfmt ",
+            b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>",
+            b"%PDF-1.7",
+        ] {
+            assert_eq!(sniff_image_type(bytes), None);
+        }
+    }
+}
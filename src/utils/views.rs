@@ -0,0 +1,230 @@
+//! Views of posts, counted in memory and written to the daily counters by the `flush_views`
+//! task, so reading a post doesn't write to the database. Each process counts on its own, so
+//! behind a load balancer a viewer may be counted once per instance.
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    hash::{BuildHasher, RandomState},
+    mem,
+    net::{IpAddr, SocketAddr},
+    sync::{Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use actix_web::{http::header, HttpRequest};
+use chrono::{NaiveDate, Utc};
+use entity::post;
+
+use super::constants::{get_trust_forwarded_for, get_view_dedup_window_secs};
+
+/// Longest referrer host kept, the size of the database column
+const MAX_REFERRER_LENGTH: usize = 255;
+/// Most referring hosts of a post counted on their own per day
+const MAX_DAILY_REFERRERS: usize = 50;
+/// Referrer the views from hosts past [`MAX_DAILY_REFERRERS`] are counted for
+const OTHER_REFERRERS: &str = "(other)";
+/// Most viewers of a post told apart per day. Viewers past it all count as unique.
+const MAX_DAILY_VIEWERS: usize = 10_000;
+/// Most views whose viewers are kept to skip repeated views. The oldest are forgotten first.
+const MAX_RECENT_VIEWS: usize = 100_000;
+
+/// Who viewed a post
+#[derive(Debug, Clone, Copy, Hash)]
+enum Viewer {
+    User(i32),
+    Address(IpAddr),
+}
+
+/// Views of a post on one day which aren't written to the database yet
+#[derive(Debug, Default)]
+pub struct PendingViews {
+    pub views: i64,
+    /// Viewers counted for the first time that day
+    pub unique_viewers: i64,
+    /// Views per referring host, an empty host for views without referrer
+    pub referrers: HashMap<String, i64>,
+}
+
+/// Who viewed a post on one day and where they came from
+#[derive(Debug, Default)]
+struct Seen {
+    viewers: HashSet<u64>,
+    referrers: HashSet<String>,
+}
+
+#[derive(Debug, Default)]
+struct Counts {
+    /// When a view of each post by each viewer was counted last
+    last_counted: HashMap<(i32, u64), Instant>,
+    /// Keys of `last_counted` oldest first, along with when they were counted. Keys counted again
+    /// are also listed at their older place until it comes up.
+    counted_order: VecDeque<((i32, u64), Instant)>,
+    /// Viewers and referrers of each post per day. Past days are dropped.
+    seen: HashMap<(i32, NaiveDate), Seen>,
+    pending: HashMap<(i32, NaiveDate), PendingViews>,
+}
+
+impl Counts {
+    /// Remember the view of a post by a viewer, both in `key`, counted at `now`
+    fn counted(&mut self, key: (i32, u64), now: Instant) {
+        self.last_counted.insert(key, now);
+        self.counted_order.push_back((key, now));
+        while self.last_counted.len() > MAX_RECENT_VIEWS {
+            self.forget_oldest();
+        }
+    }
+
+    /// Forget the views counted longer than `window` ago
+    fn forget_expired(&mut self, window: Duration) {
+        while self
+            .counted_order
+            .front()
+            .is_some_and(|(_, counted)| counted.elapsed() >= window)
+        {
+            self.forget_oldest();
+        }
+    }
+
+    fn forget_oldest(&mut self) {
+        let Some((key, counted)) = self.counted_order.pop_front() else {
+            return;
+        };
+        // Unless it was counted again since
+        if self.last_counted.get(&key) == Some(&counted) {
+            self.last_counted.remove(&key);
+        }
+    }
+}
+
+/// Counter of post views shared by all workers
+#[derive(Debug, Default)]
+pub struct ViewCounter {
+    counts: Mutex<Counts>,
+    /// Viewers are only kept as hashes, salted per process so addresses can't be recovered
+    hasher: RandomState,
+}
+
+impl ViewCounter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count `req` as a view of `post` by the authenticated `user`, if any. Repeated views within
+    /// the dedup window and authors reading their own posts aren't counted.
+    pub fn record(&self, req: &HttpRequest, post: &post::Model, user: Option<i32>) {
+        if user == Some(post.user_id) {
+            return;
+        }
+        let viewer = match user {
+            Some(user) => Viewer::User(user),
+            None => match client_address(req) {
+                Some(address) => Viewer::Address(address),
+                None => return,
+            },
+        };
+        let viewer = self.hasher.hash_one(viewer);
+        let referrer = referrer_host(req);
+
+        let mut counts = self.lock();
+        let now = Instant::now();
+        let window = Duration::from_secs(get_view_dedup_window_secs());
+        if counts
+            .last_counted
+            .get(&(post.id, viewer))
+            .is_some_and(|counted| now.duration_since(*counted) < window)
+        {
+            return;
+        }
+        counts.counted((post.id, viewer), now);
+
+        let day = Utc::now().date_naive();
+        let seen = counts.seen.entry((post.id, day)).or_default();
+        let first_today = match seen.viewers.len() < MAX_DAILY_VIEWERS {
+            true => seen.viewers.insert(viewer),
+            false => !seen.viewers.contains(&viewer),
+        };
+        let referrer = match seen.referrers.contains(&referrer) {
+            true => referrer,
+            false if seen.referrers.len() < MAX_DAILY_REFERRERS => {
+                seen.referrers.insert(referrer.clone());
+                referrer
+            },
+            false => OTHER_REFERRERS.to_string(),
+        };
+        let pending = counts.pending.entry((post.id, day)).or_default();
+        pending.views += 1;
+        if first_today {
+            pending.unique_viewers += 1;
+        }
+        *pending.referrers.entry(referrer).or_default() += 1;
+    }
+
+    /// Take the views counted since the last call, keyed by post id and day. Forgets viewers who
+    /// would be counted again anyway.
+    pub fn take(&self) -> HashMap<(i32, NaiveDate), PendingViews> {
+        let mut counts = self.lock();
+        let window = Duration::from_secs(get_view_dedup_window_secs());
+        counts.forget_expired(window);
+        let today = Utc::now().date_naive();
+        counts.seen.retain(|(_, day), _| *day >= today);
+        mem::take(&mut counts.pending)
+    }
+
+    /// Put back views taken by [`ViewCounter::take`] which couldn't be written
+    pub fn restore(&self, views: HashMap<(i32, NaiveDate), PendingViews>) {
+        let mut counts = self.lock();
+        for (key, restored) in views {
+            let pending = counts.pending.entry(key).or_default();
+            pending.views += restored.views;
+            pending.unique_viewers += restored.unique_viewers;
+            for (referrer, views) in restored.referrers {
+                *pending.referrers.entry(referrer).or_default() += views;
+            }
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Counts> {
+        // The counts stay consistent enough when a thread panicked while holding the lock
+        self.counts
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Address of the client. Proxy headers are only believed with `TRUST_FORWARDED_FOR` set.
+fn client_address(req: &HttpRequest) -> Option<IpAddr> {
+    if !get_trust_forwarded_for() {
+        return req.peer_addr().map(|address| address.ip());
+    }
+    let address = req.connection_info().realip_remote_addr()?.to_string();
+    // The peer address it falls back to comes with a port
+    address.parse::<IpAddr>().ok().or_else(|| {
+        address
+            .parse::<SocketAddr>()
+            .ok()
+            .map(|address| address.ip())
+    })
+}
+
+/// Host of the page linking to the post, empty if there is none
+fn referrer_host(req: &HttpRequest) -> String {
+    let Some((_, rest)) = req
+        .headers()
+        .get(header::REFERER)
+        .and_then(|referrer| referrer.to_str().ok())
+        .and_then(|referrer| referrer.split_once("://"))
+    else {
+        return String::new();
+    };
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = authority.rsplit('@').next().unwrap_or_default();
+    let host = match host.strip_prefix('[') {
+        // IPv6 addresses are enclosed in brackets, their colons aren't a port
+        Some(ipv6) => ipv6.split(']').next().unwrap_or_default(),
+        None => host.split(':').next().unwrap_or_default(),
+    };
+    host.to_lowercase()
+        .chars()
+        .take(MAX_REFERRER_LENGTH)
+        .collect()
+}